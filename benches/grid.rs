@@ -0,0 +1,56 @@
+use bevy::utils::HashSet;
+use bevy_ecs_ldtk::prelude::GridCoords;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+// 游戏是 bin crate，直接把 grid 模块编译进 bench
+#[allow(dead_code)]
+#[path = "../src/grid.rs"]
+mod grid;
+
+use grid::{merge_grid_rects, merge_region_rects, partition_region_rects};
+
+const WIDTH: i32 = 128;
+const HEIGHT: i32 = 64;
+
+///
+/// 类似关卡的 cell：地面、两侧的墙、错开的平台，再加一些零散的方块
+///
+fn level_cells() -> HashSet<GridCoords> {
+    let mut cells = HashSet::new();
+    for x in 0..WIDTH {
+        for y in 0..HEIGHT {
+            let ground = y < 3;
+            let side_wall = !(2..WIDTH - 2).contains(&x);
+            let platform = y % 8 == 0 && (x / 6 + y / 8) % 3 != 0;
+            let scattered = (x * 7 + y * 13) % 23 == 0;
+            if ground || side_wall || platform || scattered {
+                cells.insert(GridCoords { x, y });
+            }
+        }
+    }
+    cells
+}
+
+fn bench_merge(c: &mut Criterion) {
+    let cells = level_cells();
+    c.bench_function("merge_grid_rects 128x64", |b| {
+        b.iter(|| merge_grid_rects(black_box(&cells), WIDTH, HEIGHT))
+    });
+
+    let shifted: HashSet<GridCoords> = cells
+        .iter()
+        .map(|c| GridCoords {
+            x: c.x - WIDTH / 2,
+            y: c.y + 1000,
+        })
+        .collect();
+    c.bench_function("merge_region_rects 128x64", |b| {
+        b.iter(|| merge_region_rects(black_box(&shifted)))
+    });
+    c.bench_function("partition_region_rects 128x64", |b| {
+        b.iter(|| partition_region_rects(black_box(&shifted)))
+    });
+}
+
+criterion_group!(benches, bench_merge);
+criterion_main!(benches);
@@ -1,154 +1,486 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 
-use bevy::prelude::*;
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
 use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
 
-use crate::components::Player;
+use crate::components::{Facing, Grounded, JumpFlag, Player, WallContact};
 
 ///
 /// ref: https://github.com/PhaestusFox/bevy_platformer
 ///
 /// 动画插件
+/// [update_animation_state]: 根据状态机转换条件更新状态
+/// [apply_animation_state]: 状态变化时切换 clip
 /// [animate_sprite]: 更新动画帧
-/// []
 ///
 #[derive(Debug)]
 pub struct AnimationPlugin;
 
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<AnimationResource>()
-            .add_system(animate_sprite)
-            .add_system(append_animation_for_player)
-            .add_system(change_player_animation);
+        app.add_asset::<AnimationSet>()
+            .init_asset_loader::<AnimationSetLoader>()
+            .add_event::<AnimationFinished>()
+            .add_event::<AnimationFrameEvent>()
+            .add_system(append_animation_set_for_player)
+            .add_system(start_animation)
+            .add_system(update_animation_state.label(AnimationSystem::UpdateState))
+            .add_system(
+                apply_animation_state
+                    .label(AnimationSystem::ApplyState)
+                    .after(AnimationSystem::UpdateState),
+            )
+            .add_system(
+                animate_sprite
+                    .label(AnimationSystem::Animate)
+                    .after(AnimationSystem::ApplyState),
+            )
+            .add_system(finish_one_shot_animation.after(AnimationSystem::Animate))
+            .add_system(log_frame_markers.after(AnimationSystem::Animate));
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq)]
-enum AnimationState {
+///
+/// 动画相关 system 的执行顺序
+///
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, SystemLabel)]
+pub enum AnimationSystem {
+    UpdateState,
+    ApplyState,
+    Animate,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum AnimationState {
     Idle,
     Jump,
     Run,
     Fall,
+    Hit,
+    DoubleJump,
+    WallSlide,
+    Appearing,
+    Desappearing,
+}
+
+impl AnimationState {
+    /// 对应动画清单中的 clip 名称
+    fn clip_name(&self) -> &'static str {
+        match self {
+            AnimationState::Idle => "idle",
+            AnimationState::Jump => "jump",
+            AnimationState::Run => "run",
+            AnimationState::Fall => "fall",
+            AnimationState::Hit => "hit",
+            AnimationState::DoubleJump => "double_jump",
+            AnimationState::WallSlide => "wall_slide",
+            AnimationState::Appearing => "appearing",
+            AnimationState::Desappearing => "desappearing",
+        }
+    }
+}
+
+///
+/// clip 播放方式
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum PlaybackMode {
+    /// 循环播放
+    #[default]
+    Loop,
+    /// 播放一次，停在最后一帧
+    Once,
+    /// 正放倒放来回循环
+    PingPong,
+    /// 倒序循环播放
+    Reverse,
 }
 
 #[derive(Debug, Clone, Component)]
 struct AnimationMeta {
+    name: String,
     len: usize,
     frame_time: f32,
+    mode: PlaybackMode,
+    /// (帧 index, 标记名)
+    markers: Vec<(usize, String)>,
 }
 
 impl AnimationMeta {
-    fn new(len: usize, fps: usize) -> AnimationMeta {
+    fn new(name: String, len: usize, fps: usize, mode: PlaybackMode) -> AnimationMeta {
         AnimationMeta {
+            name,
             len: len,
             frame_time: 1. / (fps as f32),
+            mode,
+            markers: Vec::new(),
         }
     }
+
+    fn with_markers(mut self, markers: HashMap<String, Vec<usize>>) -> AnimationMeta {
+        for (marker, frames) in markers {
+            for frame in frames {
+                self.markers.push((frame, marker.clone()));
+            }
+        }
+        self.markers.sort();
+        self
+    }
+
+    /// clip 开始时的帧
+    fn first_frame(&self) -> usize {
+        match self.mode {
+            PlaybackMode::Reverse => self.len.saturating_sub(1),
+            _ => 0,
+        }
+    }
+
+    fn markers_at(&self, frame: usize) -> impl Iterator<Item = &str> {
+        self.markers
+            .iter()
+            .filter(move |(index, _)| *index == frame)
+            .map(|(_, marker)| marker.as_str())
+    }
+}
+
+///
+/// 动画清单文件 `*.anim.ron` 的结构
+/// 每个 clip 描述一张 sprite sheet 以及播放参数
+///
+#[derive(Debug, Deserialize)]
+struct AnimationManifest {
+    clips: HashMap<String, ClipManifest>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClipManifest {
+    /// 相对 assets 目录的图片路径
+    sheet: String,
+    tile_size: (f32, f32),
+    columns: usize,
+    rows: usize,
+    /// 多个角色/动作共用一张图时，用 offset 指定起始像素
+    #[serde(default)]
+    offset: Option<(f32, f32)>,
+    #[serde(default)]
+    padding: Option<(f32, f32)>,
+    fps: usize,
+    #[serde(default)]
+    mode: PlaybackMode,
+    /// 标记名 -> 帧 index 列表，例如 `"footstep": [3, 9]`
+    #[serde(default)]
+    markers: HashMap<String, Vec<usize>>,
 }
 
 ///
-/// 加载动画资源 Resource
+/// 由动画清单加载得到的 asset，clip 名称 -> (atlas, meta)
 ///
-#[derive(Debug, Resource)]
-struct AnimationResource {
-    map: HashMap<AnimationState, (Handle<TextureAtlas>, AnimationMeta)>,
+/// 任意 entity 挂上 `Handle<AnimationSet>` 即可播放其中的 clip，
+/// 玩家、敌人、NPC 各自指向自己的清单
+///
+#[derive(Debug, Default, TypeUuid)]
+#[uuid = "12c6f4b3-5c80-461b-988b-e5e0ffdfe89a"]
+pub struct AnimationSet {
+    clips: HashMap<String, (Handle<TextureAtlas>, AnimationMeta)>,
 }
 
-impl AnimationResource {
-    fn add(&mut self, state: AnimationState, handle: Handle<TextureAtlas>, meta: AnimationMeta) {
-        self.map.insert(state, (handle, meta));
+impl AnimationSet {
+    fn get(&self, name: &str) -> Option<(Handle<TextureAtlas>, AnimationMeta)> {
+        self.clips.get(name).cloned()
+    }
+}
+
+///
+/// 解析 `*.anim.ron`，为每个 clip 生成带 label 的 [TextureAtlas]
+/// label 即 clip 名称，重新加载时 handle 保持不变，配合 `watch_for_changes` 热更新
+///
+#[derive(Default)]
+struct AnimationSetLoader;
+
+impl AssetLoader for AnimationSetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let manifest: AnimationManifest = ron::de::from_bytes(bytes)?;
+
+            let mut set = AnimationSet::default();
+            let mut dependencies = Vec::new();
+            for (name, clip) in manifest.clips {
+                let sheet_path = AssetPath::new(PathBuf::from(&clip.sheet), None);
+                let atlas = TextureAtlas::from_grid(
+                    load_context.get_handle(sheet_path.get_id()),
+                    Vec2::new(clip.tile_size.0, clip.tile_size.1),
+                    clip.columns,
+                    clip.rows,
+                    clip.padding.map(|(x, y)| Vec2::new(x, y)),
+                    clip.offset.map(|(x, y)| Vec2::new(x, y)),
+                );
+                let atlas = load_context.set_labeled_asset(&name, LoadedAsset::new(atlas));
+                let meta =
+                    AnimationMeta::new(name.clone(), clip.columns * clip.rows, clip.fps, clip.mode)
+                        .with_markers(clip.markers);
+                set.clips.insert(name, (atlas, meta));
+                dependencies.push(sheet_path);
+            }
+
+            load_context.set_default_asset(LoadedAsset::new(set).with_dependencies(dependencies));
+            Ok(())
+        })
     }
-    fn get(&self, state: AnimationState) -> Option<(Handle<TextureAtlas>, AnimationMeta)> {
-        self.map.get(&state).cloned()
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
     }
 }
 
-impl FromWorld for AnimationResource {
-    fn from_world(world: &mut World) -> Self {
-        let mut res = AnimationResource {
-            map: HashMap::new(),
-        };
-        world.resource_scope(|world, mut texture_atles: Mut<Assets<TextureAtlas>>| {
-            let asset_server = world.resource::<AssetServer>();
-
-            // Virtual Guy
-            let idel_atlas = TextureAtlas::from_grid(
-                asset_server.load("Main Characters/Virtual Guy/Idle (32x32).png"),
-                Vec2::splat(32.),
-                11,
-                1,
-                None,
-                None,
-            );
-            res.add(
-                AnimationState::Idle,
-                texture_atles.add(idel_atlas),
-                AnimationMeta::new(11, 20),
-            );
+/// 玩家默认使用的动画清单
+const PLAYER_ANIMATION_SET: &str = "animations/virtual_guy.anim.ron";
 
-            let run_atlas = TextureAtlas::from_grid(
-                asset_server.load("Main Characters/Virtual Guy/Run (32x32).png"),
-                Vec2::splat(32.),
-                12,
-                1,
-                None,
-                None,
-            );
-            res.add(
-                AnimationState::Run,
-                texture_atles.add(run_atlas),
-                AnimationMeta::new(12, 20),
-            );
+///
+/// 动画状态机判断转换条件时可用的信息
+/// 由 [update_animation_state] 从 entity 上已有的组件收集
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AnimationContext {
+    pub velocity: Vec2,
+    pub grounded: bool,
+    pub jump_count: usize,
+    pub wall_contact: WallContact,
+}
 
-            let jump_atlas = TextureAtlas::from_grid(
-                asset_server.load("Main Characters/Virtual Guy/Jump (32x32).png"),
-                Vec2::splat(32.),
-                1,
-                1,
-                None,
-                None,
-            );
-            res.add(
-                AnimationState::Jump,
-                texture_atles.add(jump_atlas),
-                AnimationMeta::new(1, 1),
-            );
+///
+/// 状态转换：条件满足时切换到 `to`
+/// 多个条件同时满足时取 priority 最高的
+///
+#[derive(Debug, Clone, Copy)]
+pub struct AnimationTransition {
+    pub to: AnimationState,
+    pub priority: u8,
+    pub condition: fn(&AnimationContext) -> bool,
+}
 
-            // let djump_atlas = TextureAtlas::from_grid(
-            //     asset_server.load("Main Characters/Virtual Guy/Double Jump (32x32).png"),
-            //     Vec2::splat(32.),
-            //     6,
-            //     1,
-            //     None,
-            //     None,
-            // );
-            // res.add(
-            //     Animation::GuyDubbleJump,
-            //     texture_atles.add(djump_atlas),
-            //     AnimationMeta{len:6,frame_time: 20.},
-            // );
-
-            let fall_atlas = TextureAtlas::from_grid(
-                asset_server.load("Main Characters/Virtual Guy/Fall (32x32).png"),
-                Vec2::splat(32.),
-                1,
-                1,
-                None,
-                None,
-            );
-            res.add(
-                AnimationState::Fall,
-                texture_atles.add(fall_atlas),
-                AnimationMeta::new(1, 1),
-            )
+///
+/// 正在播放的一次性动画
+/// 只有 priority 更高的转换才能打断，播完回到默认状态
+///
+#[derive(Debug, Clone, Copy)]
+struct OneShot {
+    state: AnimationState,
+    priority: u8,
+}
+
+///
+/// 动画状态机
+/// 没有任何转换满足条件时回到 `default`
+///
+#[derive(Debug, Clone, Component)]
+pub struct AnimationStateMachine {
+    current: AnimationState,
+    default: AnimationState,
+    one_shot: Option<OneShot>,
+    transitions: Vec<AnimationTransition>,
+}
+
+impl AnimationStateMachine {
+    pub fn new(default: AnimationState) -> Self {
+        Self {
+            current: default,
+            default,
+            one_shot: None,
+            transitions: Vec::new(),
+        }
+    }
+
+    pub fn with_transition(
+        mut self,
+        to: AnimationState,
+        priority: u8,
+        condition: fn(&AnimationContext) -> bool,
+    ) -> Self {
+        self.transitions.push(AnimationTransition {
+            to,
+            priority,
+            condition,
         });
-        res
+        self
     }
+
+    pub fn current(&self) -> AnimationState {
+        self.current
+    }
+
+    ///
+    /// 播放一次性动画，priority 不低于当前一次性动画时才生效
+    /// 返回是否开始播放
+    ///
+    pub fn play_once(&mut self, state: AnimationState, priority: u8) -> bool {
+        if matches!(self.one_shot, Some(one_shot) if one_shot.priority > priority) {
+            return false;
+        }
+        self.one_shot = Some(OneShot { state, priority });
+        self.current = state;
+        true
+    }
+
+    /// 一次性动画播完，回到默认状态，下一次更新再按条件转换
+    fn finish(&mut self, state: AnimationState) {
+        if matches!(self.one_shot, Some(one_shot) if one_shot.state == state) {
+            self.one_shot = None;
+            self.current = self.default;
+        }
+    }
+
+    fn next_state(&self, context: &AnimationContext) -> AnimationState {
+        let blocking = self.one_shot.map(|one_shot| one_shot.priority);
+        let transition = self
+            .transitions
+            .iter()
+            .filter(|t| blocking.map_or(true, |priority| t.priority > priority))
+            .filter(|t| (t.condition)(context))
+            .max_by_key(|t| t.priority);
+
+        match (transition, self.one_shot) {
+            (Some(transition), _) => transition.to,
+            (None, Some(one_shot)) => one_shot.state,
+            (None, None) => self.default,
+        }
+    }
+}
+
+///
+/// 玩家的动画状态机
+///
+fn player_state_machine() -> AnimationStateMachine {
+    AnimationStateMachine::new(AnimationState::Idle)
+        //Jumping if jump
+        .with_transition(AnimationState::Jump, 2, |ctx| ctx.velocity.y > 0.01)
+        // 第二段跳播放空翻，Once 播完停在最后一帧直到下落
+        .with_transition(AnimationState::DoubleJump, 3, |ctx| {
+            ctx.velocity.y > 0.01 && ctx.jump_count >= 2
+        })
+        // 贴墙下滑
+        .with_transition(AnimationState::WallSlide, 3, |ctx| {
+            ctx.velocity.y < -0.01 && !ctx.grounded && ctx.wall_contact != WallContact::None
+        })
+        //Falling if no on ground
+        .with_transition(AnimationState::Fall, 2, |ctx| ctx.velocity.y < -0.01)
+        // if any move keys pressed set run sprite
+        .with_transition(AnimationState::Run, 1, |ctx| ctx.velocity.x != 0.0)
+}
+
+///
+/// 非循环 clip 播放到最后一帧时发出
+///
+#[derive(Debug, Clone)]
+pub struct AnimationFinished {
+    pub entity: Entity,
+    pub clip: String,
+}
+
+///
+/// clip 播放到带标记的帧时发出，用于同步脚步声、粒子等
+/// 标记在动画清单的 `markers` 中声明
+///
+#[derive(Debug, Clone)]
+pub struct AnimationFrameEvent {
+    pub entity: Entity,
+    pub clip: String,
+    pub marker: String,
 }
 
 #[derive(Component)]
-struct FrameTime(pub f32);
+struct FrameTime {
+    elapsed: f32,
+    finished: bool,
+    /// 第一帧的标记是否已经发出
+    started: bool,
+    /// PingPong 当前是否正向播放
+    forward: bool,
+}
+
+impl FrameTime {
+    fn new() -> FrameTime {
+        FrameTime {
+            elapsed: 0.0,
+            finished: false,
+            started: false,
+            forward: true,
+        }
+    }
+}
+
+///
+/// 播放控制，可选组件：倍速、暂停
+/// 没有该组件的 entity 按 1 倍速播放
+///
+#[derive(Debug, Clone, Copy, Component)]
+pub struct AnimationPlayback {
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl Default for AnimationPlayback {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            paused: false,
+        }
+    }
+}
+
+// 给过场、暂停菜单等控制动画用，游戏里还没有调用方
+#[allow(dead_code)]
+impl AnimationPlayback {
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+}
+
+///
+/// 按播放方式推进一帧
+/// 返回 false 表示 [PlaybackMode::Once] 已经停在最后一帧
+///
+fn advance_frame(mode: PlaybackMode, len: usize, index: &mut usize, forward: &mut bool) -> bool {
+    if len <= 1 {
+        return mode != PlaybackMode::Once;
+    }
+    match mode {
+        PlaybackMode::Loop => *index = (*index + 1) % len,
+        PlaybackMode::Once => {
+            if *index + 1 >= len {
+                return false;
+            }
+            *index += 1;
+        }
+        PlaybackMode::PingPong => {
+            if *forward && *index + 1 >= len {
+                *forward = false;
+            } else if !*forward && *index == 0 {
+                *forward = true;
+            }
+            if *forward {
+                *index += 1;
+            } else {
+                *index -= 1;
+            }
+        }
+        PlaybackMode::Reverse => *index = (*index + len - 1) % len,
+    }
+    true
+}
 
 ///
 /// 添加 [PhoxAnimationBundle] 到指定 entity，就可以播放动画
@@ -165,96 +497,454 @@ impl PhoxAnimationBundle {
     fn new(animaiton: AnimationMeta) -> PhoxAnimationBundle {
         PhoxAnimationBundle {
             animaiton,
-            frame_time: FrameTime(0.0),
+            frame_time: FrameTime::new(),
         }
     }
 }
 
 ///
 /// 更新 [PhoxAnimationBundle] 内 [FrameTime]
-/// 根据 frame_time 计算动画 index
+/// 按 frame_time 累计逐帧推进，卡顿时一次推进多帧但不丢失余量
 /// 更新 [TextureAtlasSprite] index
 ///
 fn animate_sprite(
-    mut animations: Query<(&mut TextureAtlasSprite, &AnimationMeta, &mut FrameTime)>,
+    mut animations: Query<(
+        Entity,
+        &mut TextureAtlasSprite,
+        &AnimationMeta,
+        &mut FrameTime,
+        Option<&AnimationPlayback>,
+    )>,
     time: Res<Time>,
+    mut finished: EventWriter<AnimationFinished>,
+    mut frame_events: EventWriter<AnimationFrameEvent>,
 ) {
-    for (mut sprite, animation, mut frame_time) in animations.iter_mut() {
-        let delt = time.delta_seconds();
-        frame_time.0 += delt;
-        if frame_time.0 > animation.frame_time {
-            let frames = (frame_time.0 / animation.frame_time) as usize;
-            sprite.index += frames;
-            if sprite.index >= animation.len {
-                sprite.index %= animation.len;
+    for (entity, mut sprite, animation, mut frame_time, playback) in animations.iter_mut() {
+        let mut send_markers = |frame: usize| {
+            for marker in animation.markers_at(frame) {
+                frame_events.send(AnimationFrameEvent {
+                    entity,
+                    clip: animation.name.clone(),
+                    marker: marker.to_string(),
+                });
+            }
+        };
+
+        if !frame_time.started {
+            frame_time.started = true;
+            send_markers(sprite.index);
+        }
+        let playback = playback.copied().unwrap_or_default();
+        if frame_time.finished || playback.paused {
+            continue;
+        }
+
+        frame_time.elapsed += time.delta_seconds() * playback.speed.max(0.);
+        while frame_time.elapsed >= animation.frame_time {
+            frame_time.elapsed -= animation.frame_time;
+
+            let mut forward = frame_time.forward;
+            let advanced = advance_frame(
+                animation.mode,
+                animation.len,
+                &mut sprite.index,
+                &mut forward,
+            );
+            frame_time.forward = forward;
+            if advanced {
+                // 跳过的帧上的标记也要发出
+                send_markers(sprite.index);
+            } else {
+                frame_time.elapsed = 0.;
+                frame_time.finished = true;
+                finished.send(AnimationFinished {
+                    entity,
+                    clip: animation.name.clone(),
+                });
+                break;
             }
-            frame_time.0 -= animation.frame_time;
         }
     }
 }
 
 ///
-/// 为没有 [AnimationMeta] 的 [Player] entity 添加动画信息
+/// 为还没有动画集合的 [Player] 指定默认清单和状态机
 ///
-fn append_animation_for_player(
+fn append_animation_set_for_player(
     mut commands: Commands,
-    mut query: Query<Entity, (With<Player>, Without<AnimationMeta>)>,
-    animations: Res<AnimationResource>,
+    query: Query<Entity, (With<Player>, Without<Handle<AnimationSet>>)>,
+    asset_server: Res<AssetServer>,
 ) {
-    if query.is_empty() {
-        return;
+    for entity in &query {
+        commands
+            .entity(entity)
+            .insert(asset_server.load::<AnimationSet, _>(PLAYER_ANIMATION_SET))
+            .insert(player_state_machine());
     }
-    let entity = query.single_mut();
-
-    let Some((_texture_atlas, animation)) = animations.get(AnimationState::Idle) else {    error!("Failed to find animation: Idle");        return;};
-
-    commands
-        .entity(entity)
-        .insert(PhoxAnimationBundle::new(animation));
 }
 
 ///
-/// 更新动画状态
+/// 为有 `Handle<AnimationSet>` 但没有 [AnimationMeta] 的 entity 添加动画信息
+/// 没有状态机的 entity 补一个只有 Idle 的状态机
 ///
-fn change_player_animation(
-    mut player: Query<
+fn start_animation(
+    mut commands: Commands,
+    mut query: Query<
         (
-            &Player,
-            &mut Handle<TextureAtlas>,
-            &mut AnimationMeta,
+            Entity,
+            &Handle<AnimationSet>,
             &mut TextureAtlasSprite,
-            &Velocity,
+            Option<&AnimationStateMachine>,
         ),
-        (With<Player>, With<AnimationMeta>),
+        Without<AnimationMeta>,
     >,
-    animaitons: Res<AnimationResource>,
+    animation_sets: Res<Assets<AnimationSet>>,
+) {
+    for (entity, set_handle, mut sprite, state_machine) in &mut query {
+        // 动画清单还在加载中
+        let Some(set) = animation_sets.get(set_handle) else {
+            continue;
+        };
+        let state = state_machine.map_or(AnimationState::Idle, |machine| machine.current());
+        let Some((texture_atlas, animation)) = set.get(state.clip_name()) else {
+            error!("Failed to find animation: {:?}", state);
+            continue;
+        };
+
+        sprite.index = animation.first_frame();
+        let mut entity = commands.entity(entity);
+        entity
+            .insert(texture_atlas)
+            .insert(PhoxAnimationBundle::new(animation));
+        if state_machine.is_none() {
+            entity.insert(AnimationStateMachine::new(AnimationState::Idle));
+        }
+    }
+}
+
+///
+/// 根据转换条件更新状态机，并按朝向翻转 sprite
+///
+fn update_animation_state(
+    mut query: Query<(
+        &mut AnimationStateMachine,
+        &mut TextureAtlasSprite,
+        Option<&Velocity>,
+        Option<&Grounded>,
+        Option<&JumpFlag>,
+        Option<&WallContact>,
+        Option<&Facing>,
+    )>,
 ) {
-    if player.is_empty() {
-        return;
+    for (mut state_machine, mut sprite, velocity, grounded, jump_flag, wall_contact, facing) in
+        &mut query
+    {
+        let context = AnimationContext {
+            velocity: velocity.map_or(Vec2::ZERO, |v| v.linvel),
+            grounded: grounded.map_or(true, Grounded::is_grounded),
+            jump_count: jump_flag.map_or(0, |j| j.jump_count),
+            wall_contact: wall_contact.copied().unwrap_or_default(),
+        };
+
+        // 有 Facing 的 entity 按朝向翻转，否则按速度
+        if let Some(facing) = facing {
+            let flip_x = *facing == Facing::Left;
+            if sprite.flip_x != flip_x {
+                sprite.flip_x = flip_x;
+            }
+        } else if context.velocity.x < -0.1 {
+            sprite.flip_x = true;
+        } else if context.velocity.x > 0.1 {
+            sprite.flip_x = false;
+        }
+
+        let next = state_machine.next_state(&context);
+        if let Some(one_shot) = state_machine.one_shot {
+            // 被更高优先级的转换打断
+            if next != one_shot.state {
+                state_machine.one_shot = None;
+            }
+        }
+        if next != state_machine.current {
+            state_machine.current = next;
+        }
     }
-    let (_player, mut atlas, mut animation, mut sprite, velocity) = player.single_mut();
-    if velocity.linvel.x < -0.1 {
-        sprite.flip_x = true;
-    } else if velocity.linvel.x > 0.1 {
-        sprite.flip_x = false;
+}
+
+///
+/// 音效、粒子接入之前，先把帧标记打到 debug 日志里方便核对
+///
+fn log_frame_markers(mut frame_events: EventReader<AnimationFrameEvent>) {
+    for event in frame_events.iter() {
+        debug!(
+            "{:?} reached marker {} of clip {}",
+            event.entity, event.marker, event.clip
+        );
     }
+}
 
-    let set = if velocity.linvel.y > 0.01 {
-        //Jumping if jump
-        AnimationState::Jump
-    } else if velocity.linvel.y < -0.01 {
-        //Falling if no on ground
-        AnimationState::Fall
-    } else if velocity.linvel.x != 0.0 {
-        // Animation::MaskFall
-        // if any move keys pressed set run sprite
-        AnimationState::Run
-    } else {
-        AnimationState::Idle
-    };
-
-    let Some((new_atlas, new_animaiton)) = animaitons.get(set) else {error!("No Animation Jump Loaded"); return;};
-    *atlas = new_atlas;
-    sprite.index %= new_animaiton.len;
-    *animation = new_animaiton;
+///
+/// 一次性动画播完后回到默认状态
+///
+fn finish_one_shot_animation(
+    mut finished: EventReader<AnimationFinished>,
+    mut query: Query<&mut AnimationStateMachine>,
+) {
+    for event in finished.iter() {
+        if let Ok(mut state_machine) = query.get_mut(event.entity) {
+            if let Some(one_shot) = state_machine.one_shot {
+                if one_shot.state.clip_name() == event.clip {
+                    state_machine.finish(one_shot.state);
+                }
+            }
+        }
+    }
+}
+
+///
+/// 状态变化或清单热更新后，切换 atlas 和 [AnimationMeta]
+/// 清单里没有的 clip 退回 Idle，敌人不一定有跳跃动画
+///
+fn apply_animation_state(
+    mut query: Query<(
+        &AnimationStateMachine,
+        &Handle<AnimationSet>,
+        &mut Handle<TextureAtlas>,
+        &mut AnimationMeta,
+        &mut FrameTime,
+        &mut TextureAtlasSprite,
+    )>,
+    animation_sets: Res<Assets<AnimationSet>>,
+    mut set_events: EventReader<AssetEvent<AnimationSet>>,
+) {
+    let modified: Vec<Handle<AnimationSet>> = set_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.clone_weak()),
+            _ => None,
+        })
+        .collect();
+
+    for (state_machine, set_handle, mut atlas, mut animation, mut frame_time, mut sprite) in
+        &mut query
+    {
+        let state = state_machine.current();
+        let reloaded = modified.contains(set_handle);
+        if animation.name == state.clip_name() && !reloaded {
+            continue;
+        }
+
+        let Some(set) = animation_sets.get(set_handle) else {
+            continue;
+        };
+        let Some((new_atlas, new_animaiton)) = set
+            .get(state.clip_name())
+            .or_else(|| set.get(AnimationState::Idle.clip_name()))
+        else {
+            error!("No Animation {:?} Loaded", state);
+            continue;
+        };
+        if animation.name == new_animaiton.name && !reloaded {
+            continue;
+        }
+
+        *atlas = new_atlas;
+        if animation.name != new_animaiton.name {
+            sprite.index = new_animaiton.first_frame();
+            *frame_time = FrameTime::new();
+        } else {
+            sprite.index %= new_animaiton.len;
+        }
+        *animation = new_animaiton;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::event::ManualEventReader, utils::Duration};
+
+    use super::*;
+
+    #[test]
+    fn advance_frame_loop_wraps() {
+        let (mut index, mut forward) = (0, true);
+        let frames: Vec<usize> = (0..5)
+            .map(|_| {
+                assert!(advance_frame(
+                    PlaybackMode::Loop,
+                    3,
+                    &mut index,
+                    &mut forward
+                ));
+                index
+            })
+            .collect();
+        assert_eq!(frames, vec![1, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn advance_frame_once_stops_on_last_frame() {
+        let (mut index, mut forward) = (0, true);
+        assert!(advance_frame(
+            PlaybackMode::Once,
+            3,
+            &mut index,
+            &mut forward
+        ));
+        assert!(advance_frame(
+            PlaybackMode::Once,
+            3,
+            &mut index,
+            &mut forward
+        ));
+        assert_eq!(index, 2);
+        assert!(!advance_frame(
+            PlaybackMode::Once,
+            3,
+            &mut index,
+            &mut forward
+        ));
+        assert_eq!(index, 2);
+    }
+
+    #[test]
+    fn advance_frame_ping_pong_bounces() {
+        let (mut index, mut forward) = (0, true);
+        let frames: Vec<usize> = (0..7)
+            .map(|_| {
+                advance_frame(PlaybackMode::PingPong, 3, &mut index, &mut forward);
+                index
+            })
+            .collect();
+        assert_eq!(frames, vec![1, 2, 1, 0, 1, 2, 1]);
+    }
+
+    #[test]
+    fn advance_frame_reverse_wraps() {
+        let meta = AnimationMeta::new("run".to_string(), 3, 8, PlaybackMode::Reverse);
+        let (mut index, mut forward) = (meta.first_frame(), true);
+        assert_eq!(index, 2);
+        let frames: Vec<usize> = (0..4)
+            .map(|_| {
+                advance_frame(PlaybackMode::Reverse, 3, &mut index, &mut forward);
+                index
+            })
+            .collect();
+        assert_eq!(frames, vec![1, 0, 2, 1]);
+    }
+
+    #[test]
+    fn advance_frame_single_frame_clip() {
+        let (mut index, mut forward) = (0, true);
+        assert!(advance_frame(
+            PlaybackMode::Loop,
+            1,
+            &mut index,
+            &mut forward
+        ));
+        assert!(!advance_frame(
+            PlaybackMode::Once,
+            1,
+            &mut index,
+            &mut forward
+        ));
+        assert_eq!(index, 0);
+    }
+
+    /// 只有 [animate_sprite] 的 app，Time 由测试手动推进
+    fn animation_app() -> App {
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.insert_resource(time)
+            .add_event::<AnimationFinished>()
+            .add_event::<AnimationFrameEvent>()
+            .add_system(animate_sprite);
+        app
+    }
+
+    fn spawn_clip(app: &mut App, len: usize, mode: PlaybackMode) -> Entity {
+        // 8 fps，每帧 0.125 秒，浮点数可以精确表示
+        let meta = AnimationMeta::new("clip".to_string(), len, 8, mode);
+        app.world
+            .spawn((
+                TextureAtlasSprite::new(meta.first_frame()),
+                PhoxAnimationBundle::new(meta),
+            ))
+            .id()
+    }
+
+    fn advance(app: &mut App, seconds: f32) {
+        let mut time = app.world.resource_mut::<Time>();
+        let last_update = time.last_update().unwrap();
+        time.update_with_instant(last_update + Duration::from_secs_f32(seconds));
+        app.update();
+    }
+
+    fn frame(app: &App, entity: Entity) -> usize {
+        app.world.get::<TextureAtlasSprite>(entity).unwrap().index
+    }
+
+    #[test]
+    fn long_hitch_advances_several_frames_and_keeps_remainder() {
+        let mut app = animation_app();
+        let entity = spawn_clip(&mut app, 8, PlaybackMode::Loop);
+
+        // 3.5 帧
+        advance(&mut app, 0.4375);
+        assert_eq!(frame(&app, entity), 3);
+        let elapsed = app.world.get::<FrameTime>(entity).unwrap().elapsed;
+        assert!((elapsed - 0.0625).abs() < 1e-6);
+
+        // 余下的半帧加上新的半帧正好推进一帧
+        advance(&mut app, 0.0625);
+        assert_eq!(frame(&app, entity), 4);
+    }
+
+    #[test]
+    fn speed_scales_and_paused_freezes_playback() {
+        let mut app = animation_app();
+        let entity = spawn_clip(&mut app, 8, PlaybackMode::Loop);
+        app.world.entity_mut(entity).insert(AnimationPlayback {
+            speed: 2.0,
+            paused: false,
+        });
+
+        advance(&mut app, 0.125);
+        assert_eq!(frame(&app, entity), 2);
+
+        app.world
+            .get_mut::<AnimationPlayback>(entity)
+            .unwrap()
+            .pause();
+        advance(&mut app, 1.0);
+        assert_eq!(frame(&app, entity), 2);
+
+        app.world
+            .get_mut::<AnimationPlayback>(entity)
+            .unwrap()
+            .resume();
+        advance(&mut app, 0.0625);
+        assert_eq!(frame(&app, entity), 3);
+    }
+
+    #[test]
+    fn once_sends_finished_exactly_once() {
+        let mut app = animation_app();
+        let entity = spawn_clip(&mut app, 3, PlaybackMode::Once);
+        let mut reader = ManualEventReader::<AnimationFinished>::default();
+
+        let mut finished = 0;
+        for _ in 0..6 {
+            advance(&mut app, 0.25);
+            let events = app.world.resource::<Events<AnimationFinished>>();
+            finished += reader
+                .iter(events)
+                .filter(|event| event.entity == entity && event.clip == "clip")
+                .count();
+        }
+        assert_eq!(finished, 1);
+        assert_eq!(frame(&app, entity), 2);
+        assert!(app.world.get::<FrameTime>(entity).unwrap().finished);
+    }
 }
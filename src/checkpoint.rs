@@ -0,0 +1,323 @@
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::components::{
+    Crouch, DashState, DropThrough, Grounded, Health, JumpFlag, MoveIntent, Player,
+};
+use crate::effect::{Effect, SpawnEffect};
+use crate::hazard::PlayerKilled;
+use crate::player::{standing_collider, JumpTimers, KinematicMotion};
+
+///
+/// 存档点插件
+/// 玩家碰到 Ldtk `Checkpoint` entity 时记录重生点，死亡或掉出 level 后在重生点复活
+///
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<CheckpointBundle>("Checkpoint")
+            .init_resource::<Respawn>()
+            // 出生点需要 GlobalTransform，等 transform 传播完再记录
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                record_spawn_point.after(TransformSystem::TransformPropagate),
+            )
+            .add_system(activate_checkpoint)
+            .add_system(fall_out_of_level)
+            .add_system(respawn_player);
+    }
+}
+
+/// 低于 level 底边多少像素算掉出 level
+const FALL_MARGIN: f32 = 32.;
+
+///
+/// 存档点，第一次碰到时激活
+///
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug, Component)]
+pub struct Checkpoint {
+    pub activated: bool,
+}
+
+///
+/// 最近激活的重生点，还没有存档点时是玩家的出生点
+///
+#[derive(Clone, Default, Debug, Resource)]
+pub struct Respawn {
+    /// 重生点所在 level 的 iid，还没记录时为 None
+    pub level_iid: Option<String>,
+    /// 重生点的 world 坐标
+    pub position: Vec2,
+}
+
+#[derive(Bundle, Default, LdtkEntity)]
+struct CheckpointBundle {
+    checkpoint: Checkpoint,
+
+    #[bundle]
+    sensor: CheckpointSensorBundle,
+}
+
+#[derive(Bundle)]
+struct CheckpointSensorBundle {
+    collider: Collider,
+    sensor: Sensor,
+}
+
+impl Default for CheckpointSensorBundle {
+    fn default() -> Self {
+        Self {
+            collider: Collider::cuboid(8., 16.),
+            sensor: Sensor,
+        }
+    }
+}
+
+///
+/// Ldtk entity 所在 level 的 iid
+/// entity 的 parent 是 layer，grandparent 才是 level
+///
+fn level_iid(
+    parent: &Parent,
+    parent_query: &Query<&Parent>,
+    level_query: &Query<&Handle<LdtkLevel>>,
+    levels: &Assets<LdtkLevel>,
+) -> Option<String> {
+    let level_entity = parent_query.get(parent.get()).ok()?.get();
+    let level = levels.get(level_query.get(level_entity).ok()?)?;
+    Some(level.level.iid.clone())
+}
+
+///
+/// 记录玩家出生点作为第一个重生点
+/// level 还没加载好时找不到 iid，之后每帧重试直到记录成功
+///
+fn record_spawn_point(
+    player_query: Query<(&GlobalTransform, &Parent), With<Player>>,
+    parent_query: Query<&Parent>,
+    level_query: Query<&Handle<LdtkLevel>>,
+    levels: Res<Assets<LdtkLevel>>,
+    mut respawn: ResMut<Respawn>,
+) {
+    if respawn.level_iid.is_some() {
+        return;
+    }
+    for (transform, parent) in &player_query {
+        let Some(level_iid) = level_iid(parent, &parent_query, &level_query, &levels) else {
+            continue;
+        };
+        *respawn = Respawn {
+            level_iid: Some(level_iid),
+            position: transform.translation().truncate(),
+        };
+    }
+}
+
+fn activate_checkpoint(
+    player_query: Query<Entity, With<Player>>,
+    mut checkpoint_query: Query<(&mut Checkpoint, &GlobalTransform, &Parent)>,
+    parent_query: Query<&Parent>,
+    level_query: Query<&Handle<LdtkLevel>>,
+    levels: Res<Assets<LdtkLevel>>,
+    rapier_context: Res<RapierContext>,
+    mut respawn: ResMut<Respawn>,
+) {
+    for player in &player_query {
+        for (collider1, collider2, intersecting) in rapier_context.intersections_with(player) {
+            if !intersecting {
+                continue;
+            }
+            let other = if collider1 == player {
+                collider2
+            } else {
+                collider1
+            };
+            let Ok((mut checkpoint, transform, parent)) = checkpoint_query.get_mut(other) else {
+                continue;
+            };
+            if checkpoint.activated {
+                continue;
+            }
+
+            checkpoint.activated = true;
+            *respawn = Respawn {
+                level_iid: level_iid(parent, &parent_query, &level_query, &levels),
+                position: transform.translation().truncate(),
+            };
+        }
+    }
+}
+
+///
+/// 玩家在 x 处低于这条线算掉出 level
+/// 取左右覆盖 x 的 level 里最低的底边，玩家走进下方的相邻 level 不会误判
+/// 没有 level 覆盖 x 时取所有 level 里最低的底边
+/// levels 为已加载 level 左下角的 world 坐标和宽度
+///
+fn fall_line(x: f32, levels: impl IntoIterator<Item = (Vec2, f32)>) -> Option<f32> {
+    let mut covering: Option<f32> = None;
+    let mut lowest: Option<f32> = None;
+    for (corner, width) in levels {
+        lowest = Some(lowest.map_or(corner.y, |bottom| bottom.min(corner.y)));
+        if (corner.x..=corner.x + width).contains(&x) {
+            covering = Some(covering.map_or(corner.y, |bottom| bottom.min(corner.y)));
+        }
+    }
+    covering.or(lowest).map(|bottom| bottom - FALL_MARGIN)
+}
+
+///
+/// 玩家掉到所在位置的 level 底边以下，当作死亡处理
+/// 玩家始终是出生 level 的子 entity，不能按 parent 判断所在 level
+/// level 的 transform 在它的左下角
+///
+fn fall_out_of_level(
+    mut player_query: Query<(Entity, &GlobalTransform, &mut Health), With<Player>>,
+    level_query: Query<(&GlobalTransform, &Handle<LdtkLevel>)>,
+    levels: Res<Assets<LdtkLevel>>,
+    mut killed_events: EventWriter<PlayerKilled>,
+) {
+    for (entity, transform, mut health) in &mut player_query {
+        if health.is_dead() {
+            continue;
+        }
+        let position = transform.translation();
+        let loaded = level_query.iter().filter_map(|(level_transform, handle)| {
+            let level = &levels.get(handle)?.level;
+            Some((
+                level_transform.translation().truncate(),
+                level.px_wid as f32,
+            ))
+        });
+        let Some(line) = fall_line(position.x, loaded) else {
+            continue;
+        };
+        if position.y < line {
+            health.kill();
+            killed_events.send(PlayerKilled { entity });
+        }
+    }
+}
+
+///
+/// 玩家死亡后回到重生点复活，清空所有移动状态
+/// 还没记录重生点时原地复活，避免玩家一直处于死亡状态
+///
+#[allow(clippy::type_complexity)]
+fn respawn_player(
+    mut commands: Commands,
+    mut killed_events: EventReader<PlayerKilled>,
+    mut player_query: Query<
+        (
+            &mut Transform,
+            &GlobalTransform,
+            &Parent,
+            &mut Velocity,
+            &mut JumpFlag,
+            &mut JumpTimers,
+            &mut Grounded,
+            &mut Health,
+            &mut DashState,
+            &mut DropThrough,
+            &mut MoveIntent,
+            &mut Crouch,
+            Option<&mut KinematicMotion>,
+        ),
+        With<Player>,
+    >,
+    global_query: Query<&GlobalTransform, Without<Player>>,
+    respawn: Res<Respawn>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut effects: EventWriter<SpawnEffect>,
+) {
+    for event in killed_events.iter() {
+        let Ok((
+            mut transform,
+            global_transform,
+            parent,
+            mut velocity,
+            mut jump_flag,
+            mut timers,
+            mut grounded,
+            mut health,
+            mut dash,
+            mut drop_through,
+            mut intent,
+            mut crouch,
+            motion,
+        )) = player_query.get_mut(event.entity)
+        else {
+            continue;
+        };
+
+        if let (Some(level_iid), Ok(parent_transform)) =
+            (&respawn.level_iid, global_query.get(parent.get()))
+        {
+            effects.send(SpawnEffect::at(
+                Effect::Desappearing,
+                global_transform.translation(),
+            ));
+
+            // 重生点是 world 坐标，换算到玩家 parent 的坐标系
+            let position = respawn.position.extend(global_transform.translation().z);
+            transform.translation = parent_transform
+                .affine()
+                .inverse()
+                .transform_point3(position);
+
+            let selected = LevelSelection::Iid(level_iid.clone());
+            if *level_selection != selected {
+                *level_selection = selected;
+            }
+        }
+
+        *velocity = Velocity::zero();
+        // kinematic 模式下真正的速度在 KinematicMotion 里
+        if let Some(mut motion) = motion {
+            *motion = KinematicMotion::default();
+        }
+        // 死亡时的冲刺、缓冲的跳跃和下蹲都不带到重生点
+        jump_flag.clear();
+        *timers = JumpTimers::default();
+        *grounded = Grounded::default();
+        dash.cancel();
+        *drop_through = DropThrough::default();
+        *intent = MoveIntent::default();
+        if crouch.crouching {
+            crouch.crouching = false;
+            commands.entity(event.entity).insert(standing_collider());
+        }
+        health.revive();
+
+        effects.send(SpawnEffect::attached(Effect::Appearing, event.entity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fall_line_uses_level_under_player() {
+        // 右边相邻的 level 低 200 像素
+        let levels = [(Vec2::new(0., 0.), 256.), (Vec2::new(256., -200.), 256.)];
+        assert_eq!(fall_line(100., levels), Some(-FALL_MARGIN));
+        assert_eq!(fall_line(300., levels), Some(-200. - FALL_MARGIN));
+    }
+
+    #[test]
+    fn fall_line_uses_lowest_of_stacked_levels() {
+        // 上下叠放的两个 level，从上面掉进下面的 level 不算掉出
+        let levels = [(Vec2::new(0., 256.), 256.), (Vec2::new(0., 0.), 256.)];
+        assert_eq!(fall_line(100., levels), Some(-FALL_MARGIN));
+    }
+
+    #[test]
+    fn fall_line_outside_all_levels() {
+        let levels = [(Vec2::new(0., 0.), 256.), (Vec2::new(256., -200.), 256.)];
+        assert_eq!(fall_line(-50., levels), Some(-200. - FALL_MARGIN));
+        assert_eq!(fall_line(0., []), None);
+    }
+}
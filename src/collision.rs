@@ -1,9 +1,18 @@
 use bevy::{
+    ecs::{
+        entity::Entities,
+        system::{EntityCommands, SystemParam},
+    },
     prelude::*,
     utils::{HashMap, HashSet},
 };
 use bevy_ecs_ldtk::prelude::*;
-use bevy_rapier2d::prelude::*;
+use bevy_rapier2d::{prelude::*, rapier::math::Vector};
+
+use crate::components::DropThrough;
+use crate::grid::{
+    connected_regions, merge_region_rects, neighbors, partition_region_rects, trace_outlines,
+};
 
 ///
 /// 添加 [FixedBlock] 标记的 Ltdk cell
@@ -12,175 +21,1297 @@ use bevy_rapier2d::prelude::*;
 #[derive(Clone, Debug, Default, Component)]
 pub struct FixedBlock;
 
+///
+/// 单向平台 cell，只从上方碰撞，按下 Down 可以穿过
+///
+#[derive(Clone, Debug, Default, Component)]
+pub struct OneWayBlock;
+
+///
+/// 斜坡 cell，每个 cell 单独生成凸多边形 collision
+///
+#[derive(Clone, Debug, Component)]
+pub struct SlopeBlock(pub Slope);
+
+///
+/// 梯子 cell，生成 sensor
+///
+#[derive(Clone, Debug, Default, Component)]
+pub struct LadderBlock;
+
+///
+/// 危险区域 cell，生成 sensor，不是实心的
+///
+#[derive(Clone, Debug, Default, Component)]
+pub struct HazardBlock;
+
+///
+/// 单向平台 collider 标记，供 [OneWayPlatformHooks] 识别
+///
+#[derive(Clone, Debug, Default, Component)]
+pub struct OneWayPlatform;
+
+///
+/// 单向平台 collider 所在的 collision group
+/// kinematic controller 不经过物理钩子，穿过单向平台时用它把平台排除掉
+///
+pub const ONE_WAY_GROUP: Group = Group::GROUP_32;
+
+///
+/// 梯子 sensor 标记
+///
+#[derive(Clone, Debug, Default, Component)]
+pub struct Ladder;
+
+///
+/// 斜坡形状，Up 为向右上升
+/// 22.5° 斜坡由 Low、High 两个 cell 拼成
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slope {
+    Up45,
+    Down45,
+    Up22Low,
+    Up22High,
+    Down22High,
+    Down22Low,
+}
+
+impl Slope {
+    /// 以 cell 左下角为原点的顶点
+    fn points(&self, size: f32) -> Vec<Vec2> {
+        let half = size / 2.;
+        let (left, right) = match self {
+            Slope::Up45 => (0., size),
+            Slope::Down45 => (size, 0.),
+            Slope::Up22Low => (0., half),
+            Slope::Up22High => (half, size),
+            Slope::Down22High => (size, half),
+            Slope::Down22Low => (half, 0.),
+        };
+        let mut points = vec![Vec2::ZERO, Vec2::new(size, 0.)];
+        if right > 0. {
+            points.push(Vec2::new(size, right));
+        }
+        if left > 0. {
+            points.push(Vec2::new(0., left));
+        }
+        points
+    }
+}
+
+///
+/// IntGrid 值的碰撞类型
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileKind {
+    Solid,
+    OneWay,
+    Slope(Slope),
+    Ladder,
+    /// 尖刺、岩浆等，按矩形合并成 sensor
+    Hazard,
+}
+
+///
+/// 一种 IntGrid 值生成的 collider 属性
+///
+#[derive(Debug, Clone)]
+pub struct TileCollider {
+    pub kind: TileKind,
+    pub friction: f32,
+    /// 和玩家等其它 collider 的摩擦系数怎么合并
+    pub friction_combine_rule: CoefficientCombineRule,
+    pub restitution: f32,
+    /// 和其它 collider 的弹性系数怎么合并
+    pub restitution_combine_rule: CoefficientCombineRule,
+    pub groups: Option<CollisionGroups>,
+    pub sensor: bool,
+    /// 给生成的 collider 插入自定义标记组件
+    pub marker: Option<fn(&mut EntityCommands)>,
+}
+
+impl TileCollider {
+    pub fn new(kind: TileKind) -> Self {
+        Self {
+            kind,
+            friction: 1.0,
+            friction_combine_rule: CoefficientCombineRule::Average,
+            restitution: 0.0,
+            restitution_combine_rule: CoefficientCombineRule::Average,
+            groups: None,
+            sensor: matches!(kind, TileKind::Ladder | TileKind::Hazard),
+            marker: None,
+        }
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    pub fn with_friction_combine_rule(mut self, rule: CoefficientCombineRule) -> Self {
+        self.friction_combine_rule = rule;
+        self
+    }
+
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_restitution_combine_rule(mut self, rule: CoefficientCombineRule) -> Self {
+        self.restitution_combine_rule = rule;
+        self
+    }
+
+    // 默认配置没有用到，给自定义的 IntGrid 值使用
+    #[allow(dead_code)]
+    pub fn with_groups(mut self, groups: CollisionGroups) -> Self {
+        self.groups = Some(groups);
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_sensor(mut self, sensor: bool) -> Self {
+        self.sensor = sensor;
+        self
+    }
+
+    pub fn with_marker(mut self, marker: fn(&mut EntityCommands)) -> Self {
+        self.marker = Some(marker);
+        self
+    }
+
+    /// 给 collider entity 插入物理属性
+    fn insert_properties(&self, entity: &mut EntityCommands) {
+        if self.sensor {
+            entity.insert(Sensor);
+        } else {
+            entity.insert(RigidBody::Fixed).insert(Friction {
+                coefficient: self.friction,
+                combine_rule: self.friction_combine_rule,
+            });
+            if self.kind == TileKind::Solid {
+                entity.insert(Wall);
+            }
+            if self.restitution > 0. {
+                entity.insert(Restitution {
+                    coefficient: self.restitution,
+                    combine_rule: self.restitution_combine_rule,
+                });
+            }
+        }
+        if let Some(groups) = self.groups {
+            entity.insert(groups);
+        }
+        if let Some(marker) = self.marker {
+            marker(entity);
+        }
+    }
+}
+
+///
+/// 实心 collider 标记，贴墙检测只检测这类 collider
+///
+#[derive(Clone, Debug, Default, Component)]
+pub struct Wall;
+
+///
+/// 冰面标记，摩擦力为 0
+/// 摩擦系数按 [CoefficientCombineRule::Min] 合并，玩家自己的摩擦系数不起作用
+///
+#[derive(Clone, Debug, Default, Component)]
+pub struct Ice;
+
+///
+/// 弹跳地面标记
+///
+#[derive(Clone, Debug, Default, Component)]
+pub struct Bouncy;
+
+///
+/// 危险区域标记，玩家进入 sensor 后受到伤害或直接死亡
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Component)]
+pub enum Hazard {
+    Damage(u32),
+    Kill,
+}
+
+///
+/// 实心 cell 的 collision 生成方式
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CollisionMode {
+    /// 合并成若干 cuboid
+    #[default]
+    Rects,
+    /// 划分成数量最少的 cuboid，比 Rects 慢，适合运行时很少变化的 layer
+    // 示例地图只用默认的 Rects，给其它地图的 layer 通过 with_layer_mode 选择
+    #[allow(dead_code)]
+    MinimalRects,
+    /// 描出每个连通区域的轮廓，生成 polyline，
+    /// 没有内部接缝，角色不会在相邻 cuboid 之间卡住
+    /// 只对非 sensor 的实心 cell 生效，sensor 和危险区域仍然按 Rects 生成
+    #[allow(dead_code)]
+    Outline,
+}
+
+///
+/// IntGrid identifier 或值 -> [TileCollider]
+/// 优先按 identifier 匹配，其次按值
+/// 都没有配置、且没有 identifier 的 IntGrid 值按 `Wall` 处理
+///
+#[derive(Debug, Clone, Resource)]
+pub struct CollisionLayerConfig {
+    colliders: Vec<TileCollider>,
+    by_identifier: HashMap<String, usize>,
+    by_value: HashMap<i32, usize>,
+    /// layer identifier -> 实心 cell 的 collision 生成方式，默认 [CollisionMode::Rects]
+    layer_modes: HashMap<String, CollisionMode>,
+    /// 把相邻 level 的 cell 放到 world 下一起合并，要求 layer 在 world 中按 grid 对齐，
+    /// 没有对齐的 layer 仍然按 level 合并
+    stitch_levels: bool,
+}
+
+impl Default for CollisionLayerConfig {
+    fn default() -> Self {
+        Self {
+            colliders: Vec::new(),
+            by_identifier: HashMap::default(),
+            by_value: HashMap::default(),
+            layer_modes: HashMap::default(),
+            stitch_levels: false,
+        }
+        .with_identifier("Wall", TileCollider::new(TileKind::Solid))
+        .with_identifier("OneWay", TileCollider::new(TileKind::OneWay))
+        .with_identifier("Ladder", TileCollider::new(TileKind::Ladder))
+        .with_identifier("Slope45Up", TileCollider::new(TileKind::Slope(Slope::Up45)))
+        .with_identifier(
+            "Slope45Down",
+            TileCollider::new(TileKind::Slope(Slope::Down45)),
+        )
+        .with_identifier(
+            "Slope22UpLow",
+            TileCollider::new(TileKind::Slope(Slope::Up22Low)),
+        )
+        .with_identifier(
+            "Slope22UpHigh",
+            TileCollider::new(TileKind::Slope(Slope::Up22High)),
+        )
+        .with_identifier(
+            "Slope22DownHigh",
+            TileCollider::new(TileKind::Slope(Slope::Down22High)),
+        )
+        .with_identifier(
+            "Slope22DownLow",
+            TileCollider::new(TileKind::Slope(Slope::Down22Low)),
+        )
+        .with_identifier(
+            "Ice",
+            TileCollider::new(TileKind::Solid)
+                .with_friction(0.0)
+                .with_friction_combine_rule(CoefficientCombineRule::Min)
+                .with_marker(|entity| {
+                    entity.insert(Ice);
+                }),
+        )
+        .with_identifier(
+            "Bouncy",
+            TileCollider::new(TileKind::Solid)
+                .with_restitution(0.9)
+                .with_restitution_combine_rule(CoefficientCombineRule::Max)
+                .with_marker(|entity| {
+                    entity.insert(Bouncy);
+                }),
+        )
+        .with_identifier(
+            "Spikes",
+            TileCollider::new(TileKind::Hazard).with_marker(|entity| {
+                entity.insert(Hazard::Damage(1));
+            }),
+        )
+        .with_identifier(
+            "Lava",
+            TileCollider::new(TileKind::Hazard).with_marker(|entity| {
+                entity.insert(Hazard::Kill);
+            }),
+        )
+        .with_identifier(
+            "KillZone",
+            TileCollider::new(TileKind::Hazard).with_marker(|entity| {
+                entity.insert(Hazard::Kill);
+            }),
+        )
+    }
+}
+
+impl CollisionLayerConfig {
+    // 示例地图只有一个 IntGrid layer，使用默认的 Rects
+    #[allow(dead_code)]
+    pub fn with_layer_mode(mut self, layer_identifier: &str, mode: CollisionMode) -> Self {
+        self.layer_modes.insert(layer_identifier.to_string(), mode);
+        self
+    }
+
+    pub fn with_stitch_levels(mut self, stitch_levels: bool) -> Self {
+        self.stitch_levels = stitch_levels;
+        self
+    }
+
+    fn layer_mode(&self, layer_identifier: &str) -> CollisionMode {
+        self.layer_modes
+            .get(layer_identifier)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn with_identifier(mut self, identifier: &str, collider: TileCollider) -> Self {
+        self.colliders.push(collider);
+        self.by_identifier
+            .insert(identifier.to_string(), self.colliders.len() - 1);
+        self
+    }
+
+    // 示例地图的 IntGrid 值都有 identifier，给没有 identifier 的地图按值配置
+    #[allow(dead_code)]
+    pub fn with_value(mut self, value: i32, collider: TileCollider) -> Self {
+        self.colliders.push(collider);
+        self.by_value.insert(value, self.colliders.len() - 1);
+        self
+    }
+
+    fn lookup(&self, value: i32, identifier: Option<&String>) -> Option<usize> {
+        identifier
+            .and_then(|identifier| self.by_identifier.get(identifier))
+            .or_else(|| self.by_value.get(&value))
+            .or_else(|| match identifier {
+                Some(_) => None,
+                None => self.by_identifier.get("Wall"),
+            })
+            .copied()
+    }
+
+    fn get(&self, id: TileColliderId) -> &TileCollider {
+        &self.colliders[id.0]
+    }
+}
+
+///
+/// cell 使用的 [TileCollider] 在 [CollisionLayerConfig] 中的下标
+/// 属性相同的 cell 才会合并
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Component)]
+pub struct TileColliderId(usize);
+
 ///
 /// 为 [FixedBlock] 添加 collision
+/// 同时处理单向平台、斜坡和梯子，类型和物理属性由 [CollisionLayerConfig] 决定
+/// 运行时增删 cell 只会重建受影响的区域
 ///
 pub struct FixedBlockCollisionPlugin;
 
 impl Plugin for FixedBlockCollisionPlugin {
     fn build(&self, app: &mut App) {
-        app.register_ldtk_int_cell::<CellBundle>(1)
-            .add_system(spawn_fixed_block_collision);
+        app.init_resource::<CollisionLayerConfig>()
+            .init_resource::<TileCollisionRegistry>()
+            .add_system(classify_int_grid_cells)
+            // 放在 PostUpdate，才能读到 Update 里移除的 cell
+            .add_system_to_stage(CoreStage::PostUpdate, update_tile_collision)
+            .add_system(tick_drop_through);
+    }
+}
+
+///
+/// 按 [CollisionLayerConfig] 给 IntGrid cell 打上对应标记
+/// identifier 来自 Ldtk 工程里的 layer 定义
+/// 运行时修改 IntGrid 值会先移除旧标记，由 [update_tile_collision] 重建
+///
+fn classify_int_grid_cells(
+    mut commands: Commands,
+    cell_query: Query<(Entity, &IntGridCell, &Parent), Changed<IntGridCell>>,
+    layer_query: Query<&LayerMetadata>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    config: Res<CollisionLayerConfig>,
+) {
+    if cell_query.is_empty() {
+        return;
+    }
+
+    // (layer def uid, IntGrid 值) -> identifier
+    let identifiers: HashMap<(i32, i32), String> = ldtk_assets
+        .iter()
+        .flat_map(|(_, ldtk_asset)| ldtk_asset.project.defs.layers.iter())
+        .flat_map(|layer_def| {
+            layer_def.int_grid_values.iter().filter_map(|value_def| {
+                value_def
+                    .identifier
+                    .clone()
+                    .map(|identifier| ((layer_def.uid, value_def.value), identifier))
+            })
+        })
+        .collect();
+
+    for (entity, cell, parent) in &cell_query {
+        let Ok(layer) = layer_query.get(parent.get()) else {
+            continue;
+        };
+        let mut cell_entity = commands.entity(entity);
+        cell_entity.remove::<(
+            TileColliderId,
+            FixedBlock,
+            OneWayBlock,
+            SlopeBlock,
+            LadderBlock,
+            HazardBlock,
+        )>();
+
+        let identifier = identifiers.get(&(layer.layer_def_uid, cell.value));
+        let Some(index) = config.lookup(cell.value, identifier) else {
+            continue;
+        };
+        let id = TileColliderId(index);
+        cell_entity.insert(id);
+        match config.get(id).kind {
+            TileKind::Solid => {
+                cell_entity.insert(FixedBlock);
+            }
+            TileKind::OneWay => {
+                cell_entity.insert(OneWayBlock);
+            }
+            TileKind::Slope(slope) => {
+                cell_entity.insert(SlopeBlock(slope));
+            }
+            TileKind::Ladder => {
+                cell_entity.insert(LadderBlock);
+            }
+            TileKind::Hazard => {
+                cell_entity.insert(HazardBlock);
+            }
+        }
+    }
+}
+
+///
+/// layer 的 (0, 0) cell 左下角在 level 坐标系下的位置
+/// Ldtk 的 y 轴向下，layer 偏移从 level 左上角算起，layer 的高度也可能和 level 不同
+///
+fn layer_origin(level: &LdtkLevel, layer: &LayerMetadata) -> IVec2 {
+    IVec2::new(
+        layer.px_total_offset_x,
+        level.level.px_hei - layer.px_total_offset_y - layer.c_hei * layer.grid_size,
+    )
+}
+
+///
+/// level 左下角在 world 坐标系下的位置
+/// 和 [LevelSpawnBehavior::UseWorldTranslation] 摆放 level 的方式一致
+///
+fn level_world_origin(level: &LdtkLevel) -> IVec2 {
+    IVec2::new(
+        level.level.world_x,
+        -level.level.world_y - level.level.px_hei,
+    )
+}
+
+///
+/// 把 cell 合并成连续的一行（horizontal 为 true）或一列
+/// 返回 (起点, 长度)
+///
+fn merge_runs(cells: &HashSet<GridCoords>, horizontal: bool) -> Vec<(GridCoords, i32)> {
+    let mut sorted: Vec<GridCoords> = cells.iter().copied().collect();
+    if horizontal {
+        sorted.sort_by_key(|c| (c.y, c.x));
+    } else {
+        sorted.sort_by_key(|c| (c.x, c.y));
+    }
+
+    let mut runs: Vec<(GridCoords, i32)> = Vec::new();
+    for coords in sorted {
+        if let Some((start, len)) = runs.last_mut() {
+            let next = if horizontal {
+                GridCoords {
+                    x: start.x + *len,
+                    y: start.y,
+                }
+            } else {
+                GridCoords {
+                    x: start.x,
+                    y: start.y + *len,
+                }
+            };
+            if next == coords {
+                *len += 1;
+                continue;
+            }
+        }
+        runs.push((coords, 1));
     }
+    runs
 }
 
-#[derive(Debug, LdtkIntCell, Bundle)]
-struct CellBundle {
-    block: FixedBlock,
+///
+/// 一组可以合并的 cell：同一个 owner、同一个 IntGrid layer 定义、同一种 [TileCollider]
+/// owner 是 collider 的 parent，一般是 level，拼接 level 时是 world
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct TileGroup {
+    owner: Entity,
+    layer_def_uid: i32,
+    id: TileColliderId,
+}
+
+///
+/// 一块连通的 cell 和它生成的 collider
+///
+#[derive(Debug, Default)]
+struct TileRegion {
+    cells: HashSet<GridCoords>,
+    colliders: Vec<Entity>,
+}
+
+#[derive(Debug, Default)]
+struct TileGroupState {
+    grid_size: f32,
+    /// cell (0, 0) 左下角在 owner 坐标系下的位置
+    origin: Vec2,
+    mode: CollisionMode,
+    cell_regions: HashMap<GridCoords, usize>,
+    regions: HashMap<usize, TileRegion>,
+    next_region: usize,
+    added: HashSet<GridCoords>,
+    removed: HashSet<GridCoords>,
+}
+
+///
+/// 记录每个 level 已生成的 collision
+/// cell 增删时只重建相邻的连通区域，不动 level 里的其它 collider
+///
+#[derive(Debug, Default, Resource)]
+struct TileCollisionRegistry {
+    cells: HashMap<Entity, (TileGroup, GridCoords)>,
+    groups: HashMap<TileGroup, TileGroupState>,
 }
 
 ///
 /// ref: https://github.com/Trouv/bevy_ecs_ldtk
-/// 获取所有 [FixedBlock] 标记，计算合并相邻 entity，生成 collision
+/// 收集新增和移除的 cell，合并相邻 cell 生成 collision
 /// 避免大量 collision 带来的性能损耗
 ///
-fn spawn_fixed_block_collision(
+#[allow(clippy::too_many_arguments)]
+fn update_tile_collision(
     mut commands: Commands,
-    wall_query: Query<(&GridCoords, &Parent), Added<FixedBlock>>,
-    parent_query: Query<&Parent, Without<FixedBlock>>,
-    level_query: Query<(Entity, &Handle<LdtkLevel>)>,
+    added_query: Query<(Entity, &GridCoords, &TileColliderId, &Parent), Added<TileColliderId>>,
+    removed: RemovedComponents<TileColliderId>,
+    entities: &Entities,
+    parent_query: Query<&Parent, Without<IntGridCell>>,
+    layer_query: Query<&LayerMetadata>,
+    level_query: Query<&Handle<LdtkLevel>>,
     levels: Res<Assets<LdtkLevel>>,
+    config: Res<CollisionLayerConfig>,
+    mut registry: ResMut<TileCollisionRegistry>,
 ) {
-    /// Represents a wide wall that is 1 tile tall
-    /// Used to spawn wall collisions
-    #[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
-    struct Plate {
-        left: i32,
-        right: i32,
-    }
-
-    /// A simple rectangle type representing a wall of any size
-    struct Rect {
-        left: i32,
-        right: i32,
-        top: i32,
-        bottom: i32,
-    }
-
-    // Consider where the walls are
-    // storing them as GridCoords in a HashSet for quick, easy lookup
-    //
-    // The key of this map will be the entity of the level the wall belongs to.
-    // This has two consequences in the resulting collision entities:
-    // 1. it forces the walls to be split along level boundaries
-    // 2. it lets us easily add the collision entities as children of the appropriate level entity
-    let mut level_to_wall_locations: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
-
-    wall_query.for_each(|(&grid_coords, parent)| {
+    let TileCollisionRegistry { cells, groups } = &mut *registry;
+    // 不能拼接的 layer，每个只警告一次
+    let mut misaligned_layers: HashSet<Entity> = HashSet::new();
+
+    for entity in removed.iter() {
+        if let Some((group, coords)) = cells.remove(&entity) {
+            let state = groups.entry(group).or_default();
+            state.added.remove(&coords);
+            state.removed.insert(coords);
+        }
+    }
+
+    for (entity, &coords, &id, parent) in &added_query {
+        let Ok(layer) = layer_query.get(parent.get()) else {
+            continue;
+        };
         // An intgrid tile's direct parent will be a layer entity, not the level entity
         // To get the level entity, you need the tile's grandparent.
         // This is where parent_query comes in.
-        if let Ok(grandparent) = parent_query.get(parent.get()) {
-            level_to_wall_locations
-                .entry(grandparent.get())
-                .or_default()
-                .insert(grid_coords);
+        let Ok(level_entity) = parent_query.get(parent.get()).map(|p| p.get()) else {
+            continue;
+        };
+        let Some(level) = level_query
+            .get(level_entity)
+            .ok()
+            .and_then(|handle| levels.get(handle))
+        else {
+            continue;
+        };
+        let origin = layer_origin(level, layer);
+
+        // Splitting groups by level has three consequences in the resulting collision entities:
+        // 1. it forces the walls to be split along level boundaries
+        // 2. it lets us easily add the collision entities as children of the appropriate level entity
+        // 3. walls with different friction, restitution, etc. never merge together
+        // 拼接 level 时改为按 world 分组，cell 换算成 world 里的 grid 坐标，
+        // 相邻 level 边界上的墙会合并成一个 collider，跑过 level 边界不会被接缝卡住
+        // layer 在 world 中没有按 grid 对齐时换算不出整数 grid 坐标，退回按 level 分组
+        let world = parent_query.get(level_entity).map(|p| p.get());
+        let offset = level_world_origin(level) + origin;
+        let aligned =
+            offset.x.rem_euclid(layer.grid_size) == 0 && offset.y.rem_euclid(layer.grid_size) == 0;
+        if config.stitch_levels
+            && world.is_ok()
+            && !aligned
+            && misaligned_layers.insert(parent.get())
+        {
+            warn!(
+                "Layer {} of level {} is not aligned to its {}px grid in the world, \
+                colliders will not be stitched across levels",
+                layer.identifier, level.level.identifier, layer.grid_size
+            );
+        }
+        let (owner, coords, origin) = match world {
+            Ok(world) if config.stitch_levels && aligned => {
+                let coords = GridCoords {
+                    x: coords.x + offset.x / layer.grid_size,
+                    y: coords.y + offset.y / layer.grid_size,
+                };
+                (world, coords, Vec2::ZERO)
+            }
+            _ => (level_entity, coords, origin.as_vec2()),
+        };
+
+        let group = TileGroup {
+            owner,
+            layer_def_uid: layer.layer_def_uid,
+            id,
+        };
+        cells.insert(entity, (group, coords));
+        let state = groups.entry(group).or_default();
+        state.grid_size = layer.grid_size as f32;
+        state.origin = origin;
+        state.mode = config.layer_mode(&layer.identifier);
+        state.removed.remove(&coords);
+        state.added.insert(coords);
+    }
+
+    groups.retain(|group, state| {
+        // owner despawn 时 collider 作为子 entity 已经一起 despawn 了
+        if !entities.contains(group.owner) {
+            return false;
+        }
+        if state.added.is_empty() && state.removed.is_empty() {
+            return true;
+        }
+
+        // 受影响的区域：变化的 cell 本身和它相邻 cell 所在的区域
+        let changed: HashSet<GridCoords> = state.added.union(&state.removed).copied().collect();
+        let affected: HashSet<usize> = changed
+            .iter()
+            .flat_map(|&coords| neighbors(coords).into_iter().chain([coords]))
+            .filter_map(|coords| state.cell_regions.get(&coords).copied())
+            .collect();
+
+        let mut region_cells: HashSet<GridCoords> = HashSet::new();
+        for index in affected {
+            let Some(region) = state.regions.remove(&index) else {
+                continue;
+            };
+            // collider 可能已经随 world 或 level 的 despawn_descendants 一起移除
+            for collider in region.colliders {
+                if let Some(collider) = commands.get_entity(collider) {
+                    collider.despawn_recursive();
+                }
+            }
+            for coords in &region.cells {
+                state.cell_regions.remove(coords);
+            }
+            region_cells.extend(region.cells);
         }
+        for coords in state.removed.drain() {
+            region_cells.remove(&coords);
+        }
+        region_cells.extend(state.added.drain());
+
+        let tile_collider = config.get(group.id);
+        for cells in connected_regions(&region_cells) {
+            let colliders = spawn_region_colliders(
+                &mut commands,
+                group.owner,
+                tile_collider,
+                state.mode,
+                &cells,
+                state.grid_size,
+                state.origin,
+            );
+            let index = state.next_region;
+            state.next_region += 1;
+            for &coords in &cells {
+                state.cell_regions.insert(coords, index);
+            }
+            state.regions.insert(index, TileRegion { cells, colliders });
+        }
+        true
     });
 
-    if !wall_query.is_empty() {
-        level_query.for_each(|(level_entity, level_handle)| {
-            if let Some(level_walls) = level_to_wall_locations.get(&level_entity) {
-                let level = levels
-                    .get(level_handle)
-                    .expect("Level should be loaded by this point");
-
-                let LayerInstance {
-                    c_wid: width,
-                    c_hei: height,
-                    grid_size,
-                    ..
-                } = level
-                    .level
-                    .layer_instances
-                    .clone()
-                    .expect("Level asset should have layers")[0];
-
-                // combine wall tiles into flat "plates" in each individual row
-                let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
-
-                for y in 0..height {
-                    let mut row_plates: Vec<Plate> = Vec::new();
-                    let mut plate_start = None;
-
-                    // + 1 to the width so the algorithm "terminates" plates that touch the right edge
-                    for x in 0..width + 1 {
-                        match (plate_start, level_walls.contains(&GridCoords { x, y })) {
-                            (Some(s), false) => {
-                                row_plates.push(Plate {
-                                    left: s,
-                                    right: x - 1,
-                                });
-                                plate_start = None;
-                            }
-                            (None, true) => plate_start = Some(x),
-                            _ => (),
-                        }
-                    }
+    cells.retain(|_, (group, _)| groups.contains_key(group));
+}
+
+/// 单向平台 collider 的厚度，占 cell 高度的比例
+const ONE_WAY_THICKNESS: f32 = 0.25;
+
+///
+/// 给一块连通的 cell 生成 collision，返回生成的 collider
+/// - 墙按矩形合并、划分成最少的矩形，或者按轮廓生成 polyline
+/// - 危险区域按矩形合并成 sensor
+/// - 单向平台按行合并，只在 cell 顶部生成一层薄 collision
+/// - 每个斜坡 cell 生成一个凸多边形 collision
+/// - 梯子按列合并
+///
+fn spawn_region_colliders(
+    commands: &mut Commands,
+    owner: Entity,
+    tile_collider: &TileCollider,
+    mode: CollisionMode,
+    cells: &HashSet<GridCoords>,
+    grid_size: f32,
+    origin: Vec2,
+) -> Vec<Entity> {
+    let mut colliders = Vec::new();
+    let at = |x: f32, y: f32| {
+        TransformBundle::from_transform(Transform::from_xyz(origin.x + x, origin.y + y, 0.))
+    };
 
-                    plate_stack.push(row_plates);
+    // Making the collider a child of the level serves two purposes:
+    // 1. Adjusts the transforms to be relative to the level for free
+    // 2. the colliders will be despawned automatically when levels unload
+    // 拼接 level 时 owner 是 world，level 卸载时由 [update_tile_collision] 移除对应 cell
+    commands
+        .entity(owner)
+        .with_children(|level| match (tile_collider.kind, mode) {
+            // sensor 的轮廓是空心的，完全在里面时检测不到，所以只给实心墙用
+            (TileKind::Solid, CollisionMode::Outline) if !tile_collider.sensor => {
+                let mut wall = level.spawn((outline_collider(cells, grid_size), at(0., 0.)));
+                tile_collider.insert_properties(&mut wall);
+                colliders.push(wall.id());
+            }
+            (TileKind::Solid | TileKind::Hazard, _) => {
+                let wall_rects = if mode == CollisionMode::MinimalRects {
+                    partition_region_rects(cells)
+                } else {
+                    merge_region_rects(cells)
+                };
+                for wall_rect in wall_rects {
+                    let mut wall = level.spawn((
+                        Collider::cuboid(
+                            wall_rect.width() as f32 * grid_size / 2.,
+                            wall_rect.height() as f32 * grid_size / 2.,
+                        ),
+                        at(
+                            (wall_rect.left + wall_rect.right + 1) as f32 * grid_size / 2.,
+                            (wall_rect.bottom + wall_rect.top + 1) as f32 * grid_size / 2.,
+                        ),
+                    ));
+                    tile_collider.insert_properties(&mut wall);
+                    colliders.push(wall.id());
+                }
+            }
+            (TileKind::OneWay, _) => {
+                let thickness = grid_size * ONE_WAY_THICKNESS;
+                for (start, len) in merge_runs(cells, true) {
+                    let mut platform = level.spawn((
+                        Collider::cuboid(len as f32 * grid_size / 2., thickness / 2.),
+                        at(
+                            (start.x as f32 + len as f32 / 2.) * grid_size,
+                            (start.y + 1) as f32 * grid_size - thickness / 2.,
+                        ),
+                    ));
+                    // 配置了 groups 时会覆盖这里的 group，需要自己带上 ONE_WAY_GROUP
+                    platform.insert((
+                        ActiveHooks::MODIFY_SOLVER_CONTACTS,
+                        OneWayPlatform,
+                        CollisionGroups::new(ONE_WAY_GROUP, Group::ALL),
+                    ));
+                    tile_collider.insert_properties(&mut platform);
+                    colliders.push(platform.id());
+                }
+            }
+            (TileKind::Slope(slope), _) => {
+                let Some(collider) = Collider::convex_hull(&slope.points(grid_size)) else {
+                    error!("Failed to build slope collider: {:?}", slope);
+                    return;
+                };
+                for grid_coords in cells {
+                    let mut slope = level.spawn((
+                        collider.clone(),
+                        at(
+                            grid_coords.x as f32 * grid_size,
+                            grid_coords.y as f32 * grid_size,
+                        ),
+                    ));
+                    tile_collider.insert_properties(&mut slope);
+                    colliders.push(slope.id());
+                }
+            }
+            (TileKind::Ladder, _) => {
+                for (start, len) in merge_runs(cells, false) {
+                    let mut ladder = level.spawn((
+                        Collider::cuboid(grid_size / 2., len as f32 * grid_size / 2.),
+                        at(
+                            (start.x as f32 + 0.5) * grid_size,
+                            (start.y as f32 + len as f32 / 2.) * grid_size,
+                        ),
+                    ));
+                    ladder.insert(Ladder);
+                    tile_collider.insert_properties(&mut ladder);
+                    colliders.push(ladder.id());
                 }
+            }
+        });
+    colliders
+}
+
+///
+/// 所有轮廓放进同一个 polyline，每个轮廓首尾相连
+///
+fn outline_collider(cells: &HashSet<GridCoords>, grid_size: f32) -> Collider {
+    let mut vertices: Vec<Vec2> = Vec::new();
+    let mut indices: Vec<[u32; 2]> = Vec::new();
+    for outline in trace_outlines(cells) {
+        let start = vertices.len() as u32;
+        let len = outline.len() as u32;
+        vertices.extend(outline.iter().map(|point| point.as_vec2() * grid_size));
+        indices.extend((0..len).map(|i| [start + i, start + (i + 1) % len]));
+    }
+    Collider::polyline(vertices, Some(indices))
+}
+
+/// 接触法线和向上方向的夹角小于这个角度时，单向平台才产生碰撞
+const ONE_WAY_ALLOWED_ANGLE: f32 = 0.1;
+
+///
+/// 单向平台接触的状态，存在 contact pair 的 user_data 里
+/// 取值由这里自己维护，不依赖 rapier `update_as_oneway_platform` 的内部约定
+///
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum OneWayContact {
+    /// 新的接触，还没判断方向
+    Unknown,
+    /// 从上方落下，正常碰撞
+    Allowed,
+    /// 从下方或侧面进入、或者正在主动穿过，穿插结束前一直忽略
+    Forbidden,
+}
+
+impl OneWayContact {
+    fn from_user_data(user_data: u32) -> Self {
+        match user_data {
+            1 => OneWayContact::Allowed,
+            2 => OneWayContact::Forbidden,
+            _ => OneWayContact::Unknown,
+        }
+    }
+
+    fn user_data(self) -> u32 {
+        match self {
+            OneWayContact::Unknown => 0,
+            OneWayContact::Allowed => 1,
+            OneWayContact::Forbidden => 2,
+        }
+    }
+
+    ///
+    /// 按这一帧的接触推进状态，返回新状态和是否保留接触
+    /// - upward: 接触法线朝上
+    /// - separated: 所有接触点都没有穿插
+    /// - touching: 还有接触点
+    ///
+    fn next(self, upward: bool, separated: bool, touching: bool) -> (Self, bool) {
+        match self {
+            OneWayContact::Unknown if upward => (OneWayContact::Allowed, true),
+            OneWayContact::Unknown => (OneWayContact::Forbidden, false),
+            OneWayContact::Forbidden if upward && separated => (OneWayContact::Allowed, true),
+            OneWayContact::Forbidden => (OneWayContact::Forbidden, false),
+            // 接触消失后重新判断方向
+            OneWayContact::Allowed if !touching => (OneWayContact::Unknown, true),
+            OneWayContact::Allowed => (OneWayContact::Allowed, true),
+        }
+    }
+}
+
+///
+/// 单向平台的物理钩子
+/// 只保留从上方落下的接触，正在穿越（[DropThrough]）的 entity 忽略所有接触
+///
+#[derive(SystemParam)]
+pub struct OneWayPlatformHooks<'w, 's> {
+    platforms: Query<'w, 's, (), With<OneWayPlatform>>,
+    drop_through: Query<'w, 's, &'static DropThrough>,
+}
+
+impl BevyPhysicsHooks for OneWayPlatformHooks<'_, '_> {
+    fn modify_solver_contacts(&self, context: ContactModificationContextView) {
+        let (platform_is_first, other) = if self.platforms.contains(context.collider1()) {
+            (true, context.collider2())
+        } else if self.platforms.contains(context.collider2()) {
+            (false, context.collider1())
+        } else {
+            return;
+        };
 
-                // combine "plates" into rectangles across multiple rows
-                let mut rect_builder: HashMap<Plate, Rect> = HashMap::new();
-                let mut prev_row: Vec<Plate> = Vec::new();
-                let mut wall_rects: Vec<Rect> = Vec::new();
-
-                // an extra empty row so the algorithm "finishes" the rects that touch the top edge
-                plate_stack.push(Vec::new());
-
-                for (y, current_row) in plate_stack.into_iter().enumerate() {
-                    for prev_plate in &prev_row {
-                        if !current_row.contains(prev_plate) {
-                            // remove the finished rect so that the same plate in the future starts a new rect
-                            if let Some(rect) = rect_builder.remove(prev_plate) {
-                                wall_rects.push(rect);
-                            }
+        if self
+            .drop_through
+            .get(other)
+            .map_or(false, |drop_through| drop_through.is_active())
+        {
+            // 标记为禁止，计时结束后仍然穿插在平台里的接触会继续被忽略，
+            // 直到完全离开平台，不会被弹回平台上
+            *context.raw.user_data = OneWayContact::Forbidden.user_data();
+            context.raw.solver_contacts.clear();
+            return;
+        }
+
+        // 法线在 collider1 的局部坐标下，从 collider1 指向 collider2
+        let allowed_local_n1 = if platform_is_first {
+            Vector::y()
+        } else {
+            -Vector::y()
+        };
+        let upward =
+            context.raw.manifold.local_n1.dot(&allowed_local_n1) >= ONE_WAY_ALLOWED_ANGLE.cos();
+        let separated = context
+            .raw
+            .solver_contacts
+            .iter()
+            .all(|contact| contact.dist > 0.);
+        let touching = !context.raw.solver_contacts.is_empty();
+        let (state, keep) =
+            OneWayContact::from_user_data(*context.raw.user_data).next(upward, separated, touching);
+        *context.raw.user_data = state.user_data();
+        if !keep {
+            context.raw.solver_contacts.clear();
+        }
+    }
+}
+
+pub(crate) fn tick_drop_through(mut query: Query<&mut DropThrough>, time: Res<Time>) {
+    for mut drop_through in &mut query {
+        if drop_through.is_active() {
+            drop_through.tick(time.delta_seconds());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs_ldtk::ldtk::Level;
+
+    use super::*;
+
+    const GRID_SIZE: i32 = 16;
+
+    fn tile_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<LdtkLevel>()
+            .init_resource::<CollisionLayerConfig>()
+            .init_resource::<TileCollisionRegistry>()
+            .add_system_to_stage(CoreStage::PostUpdate, update_tile_collision);
+        app
+    }
+
+    fn collider_id(app: &App, identifier: &str) -> TileColliderId {
+        let config = app.world.resource::<CollisionLayerConfig>();
+        TileColliderId(config.lookup(1, Some(&identifier.to_string())).unwrap())
+    }
+
+    /// cell 都是 Wall 的 [spawn_level_with]
+    fn spawn_level(app: &mut App, coords: &[(i32, i32)]) -> (Entity, HashMap<(i32, i32), Entity>) {
+        let id = collider_id(app, "Wall");
+        spawn_level_with(app, id, coords)
+    }
+
+    ///
+    /// 4 x 3 的 level，只有一个 IntGrid layer，cell 都使用 id 对应的 [TileCollider]
+    /// 返回 level entity 和 坐标 -> cell entity
+    ///
+    fn spawn_level_with(
+        app: &mut App,
+        id: TileColliderId,
+        coords: &[(i32, i32)],
+    ) -> (Entity, HashMap<(i32, i32), Entity>) {
+        let handle = app
+            .world
+            .resource_mut::<Assets<LdtkLevel>>()
+            .add(LdtkLevel {
+                level: Level {
+                    px_wid: 4 * GRID_SIZE,
+                    px_hei: 3 * GRID_SIZE,
+                    ..default()
+                },
+                background_image: None,
+            });
+
+        let mut cells = HashMap::default();
+        let level = app
+            .world
+            .spawn((handle, TransformBundle::default()))
+            .with_children(|level| {
+                level
+                    .spawn(LayerMetadata {
+                        c_wid: 4,
+                        c_hei: 3,
+                        grid_size: GRID_SIZE,
+                        layer_def_uid: 1,
+                        identifier: "IntGrid".to_string(),
+                        ..default()
+                    })
+                    .with_children(|layer| {
+                        for &(x, y) in coords {
+                            let cell = layer
+                                .spawn((GridCoords { x, y }, IntGridCell { value: 1 }, id))
+                                .id();
+                            cells.insert((x, y), cell);
                         }
-                    }
-                    for plate in &current_row {
-                        rect_builder
-                            .entry(plate.clone())
-                            .and_modify(|e| e.top += 1)
-                            .or_insert(Rect {
-                                bottom: y as i32,
-                                top: y as i32,
-                                left: plate.left,
-                                right: plate.right,
-                            });
-                    }
-                    prev_row = current_row;
-                }
+                    });
+            })
+            .id();
+        (level, cells)
+    }
 
-                commands.entity(level_entity).with_children(|level| {
-                    // Spawn colliders for every rectangle..
-                    // Making the collider a child of the level serves two purposes:
-                    // 1. Adjusts the transforms to be relative to the level for free
-                    // 2. the colliders will be despawned automatically when levels unload
-                    for wall_rect in wall_rects {
-                        level
-                            .spawn_empty()
-                            .insert(Collider::cuboid(
-                                (wall_rect.right as f32 - wall_rect.left as f32 + 1.)
-                                    * grid_size as f32
-                                    / 2.,
-                                (wall_rect.top as f32 - wall_rect.bottom as f32 + 1.)
-                                    * grid_size as f32
-                                    / 2.,
-                            ))
-                            .insert(RigidBody::Fixed)
-                            .insert(Friction::new(1.0))
-                            .insert(Transform::from_xyz(
-                                (wall_rect.left + wall_rect.right + 1) as f32 * grid_size as f32
-                                    / 2.,
-                                (wall_rect.bottom + wall_rect.top + 1) as f32 * grid_size as f32
-                                    / 2.,
-                                0.,
-                            ))
-                            .insert(GlobalTransform::default());
-                    }
-                });
-            }
+    /// level 下所有 collider 的中心
+    fn collider_centers(app: &mut App, level: Entity) -> HashMap<Entity, Vec2> {
+        app.world
+            .query_filtered::<(Entity, &Transform, &Parent), With<Collider>>()
+            .iter(&app.world)
+            .filter(|(_, _, parent)| parent.get() == level)
+            .map(|(entity, transform, _)| (entity, transform.translation.truncate()))
+            .collect()
+    }
+
+    fn find_collider(centers: &HashMap<Entity, Vec2>, center: Vec2) -> Entity {
+        centers
+            .iter()
+            .find(|(_, c)| c.distance(center) < 1e-3)
+            .map(|(entity, _)| *entity)
+            .unwrap_or_else(|| panic!("No collider at {:?} in {:?}", center, centers))
+    }
+
+    #[test]
+    fn breaking_a_block_only_rebuilds_its_region() {
+        let mut app = tile_app();
+        // 底部一行 4 个 cell，上方另有一块 2 个 cell
+        let (level, cells) =
+            spawn_level(&mut app, &[(0, 0), (1, 0), (2, 0), (3, 0), (0, 2), (1, 2)]);
+        app.update();
+
+        let before = collider_centers(&mut app, level);
+        assert_eq!(before.len(), 2);
+        let row = find_collider(&before, Vec2::new(32., 8.));
+        let block = find_collider(&before, Vec2::new(16., 40.));
+
+        app.world.entity_mut(cells[&(2, 0)]).despawn_recursive();
+        app.update();
+
+        // 底部一行断成两段，旧的 collider 被移除，上方的块保持原来的 entity
+        let after = collider_centers(&mut app, level);
+        assert_eq!(after.len(), 3);
+        assert!(app.world.get_entity(row).is_none());
+        assert_eq!(find_collider(&after, Vec2::new(16., 40.)), block);
+        let left = find_collider(&after, Vec2::new(16., 8.));
+        let right = find_collider(&after, Vec2::new(56., 8.));
+        assert!(!before.contains_key(&left) && !before.contains_key(&right));
+
+        // registry 里的区域和场景里的 collider 一致
+        let registry = app.world.resource::<TileCollisionRegistry>();
+        assert_eq!(registry.cells.len(), 5);
+        let regions: Vec<&TileRegion> = registry
+            .groups
+            .values()
+            .flat_map(|state| state.regions.values())
+            .collect();
+        assert_eq!(regions.len(), 3);
+        let mut sizes: Vec<usize> = regions.iter().map(|region| region.cells.len()).collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 2, 2]);
+        let registered: HashSet<Entity> = regions
+            .iter()
+            .flat_map(|region| region.colliders.iter().copied())
+            .collect();
+        let spawned: HashSet<Entity> = after.keys().copied().collect();
+        assert_eq!(registered, spawned);
+    }
+
+    #[test]
+    fn removing_cells_after_colliders_were_despawned() {
+        let mut app = tile_app();
+        let (level, cells) = spawn_level(&mut app, &[(0, 0), (1, 0), (2, 0)]);
+        app.update();
+
+        // collider 被别的系统移除，registry 里还记着它
+        let row = find_collider(&collider_centers(&mut app, level), Vec2::new(24., 8.));
+        app.world.entity_mut(row).despawn_recursive();
+        app.world.entity_mut(cells[&(2, 0)]).despawn_recursive();
+        app.update();
+
+        let after = collider_centers(&mut app, level);
+        assert_eq!(after.len(), 1);
+        find_collider(&after, Vec2::new(16., 8.));
+    }
+
+    #[test]
+    fn placing_a_block_merges_neighboring_regions() {
+        let mut app = tile_app();
+        let (level, _) = spawn_level(&mut app, &[(0, 0), (2, 0)]);
+        app.update();
+        assert_eq!(collider_centers(&mut app, level).len(), 2);
+
+        let id = collider_id(&app, "Wall");
+        let layer = app
+            .world
+            .query_filtered::<Entity, With<LayerMetadata>>()
+            .single(&app.world);
+        app.world.entity_mut(layer).with_children(|layer| {
+            layer.spawn((GridCoords { x: 1, y: 0 }, IntGridCell { value: 1 }, id));
         });
+        app.update();
+
+        let after = collider_centers(&mut app, level);
+        assert_eq!(after.len(), 1);
+        find_collider(&after, Vec2::new(24., 8.));
+    }
+
+    /// L 形的墙，左下角 3 x 3 的空间里占底边和左边
+    const L_SHAPE: [(i32, i32); 5] = [(0, 0), (1, 0), (2, 0), (0, 1), (0, 2)];
+
+    #[test]
+    fn walls_become_one_polyline_in_outline_mode() {
+        let mut app = tile_app();
+        app.insert_resource(
+            CollisionLayerConfig::default().with_layer_mode("IntGrid", CollisionMode::Outline),
+        );
+        let (level, _) = spawn_level(&mut app, &L_SHAPE);
+        app.update();
+
+        let centers = collider_centers(&mut app, level);
+        assert_eq!(centers.len(), 1);
+        let wall = app.world.entity(find_collider(&centers, Vec2::ZERO));
+        let polyline = wall.get::<Collider>().unwrap().as_polyline().unwrap();
+        // L 形的轮廓有 6 个顶点，首尾相连成 6 条边
+        assert_eq!(polyline.raw.vertices().len(), 6);
+        assert_eq!(polyline.raw.indices().len(), 6);
+        assert!(wall.contains::<Wall>());
+        assert!(!wall.contains::<Sensor>());
+    }
+
+    #[test]
+    fn minimal_rects_mode_partitions_walls() {
+        let mut app = tile_app();
+        app.insert_resource(
+            CollisionLayerConfig::default().with_layer_mode("IntGrid", CollisionMode::MinimalRects),
+        );
+        // 贪心合并需要 3 个 cuboid
+        let (level, _) = spawn_level(&mut app, &[(0, 0), (0, 1), (0, 2), (1, 1)]);
+        app.update();
+
+        let centers = collider_centers(&mut app, level);
+        assert_eq!(centers.len(), 2);
+        for &collider in centers.keys() {
+            let wall = app.world.entity(collider);
+            assert!(wall.get::<Collider>().unwrap().as_cuboid().is_some());
+            assert!(wall.contains::<Wall>());
+        }
+    }
+
+    #[test]
+    fn hazards_stay_solid_sensors_in_outline_mode() {
+        let mut app = tile_app();
+        app.insert_resource(
+            CollisionLayerConfig::default().with_layer_mode("IntGrid", CollisionMode::Outline),
+        );
+        let id = collider_id(&app, "Lava");
+        let (level, _) = spawn_level_with(&mut app, id, &[(0, 0), (1, 0), (0, 1), (1, 1)]);
+        app.update();
+
+        let centers = collider_centers(&mut app, level);
+        assert_eq!(centers.len(), 1);
+        let lava = find_collider(&centers, Vec2::new(16., 16.));
+        let lava = app.world.entity(lava);
+        assert!(lava.get::<Collider>().unwrap().as_cuboid().is_some());
+        assert!(lava.contains::<Sensor>());
+        assert_eq!(lava.get::<Hazard>(), Some(&Hazard::Kill));
+        assert!(!lava.contains::<Wall>());
+    }
+
+    #[test]
+    fn lookup_prefers_identifier_then_value_then_wall() {
+        let config = CollisionLayerConfig::default()
+            .with_value(1, TileCollider::new(TileKind::OneWay))
+            .with_value(2, TileCollider::new(TileKind::Ladder));
+        let by_identifier = |identifier: &str| config.by_identifier[identifier];
+        let by_value = |value: i32| config.by_value[&value];
+
+        // identifier 优先于值
+        let ice = "Ice".to_string();
+        assert_eq!(config.lookup(1, Some(&ice)), Some(by_identifier("Ice")));
+        // 没有 identifier 时按值
+        assert_eq!(config.lookup(1, None), Some(by_value(1)));
+        assert_eq!(config.lookup(2, None), Some(by_value(2)));
+        // 值也没有配置时按 Wall
+        assert_eq!(config.lookup(7, None), Some(by_identifier("Wall")));
+        // identifier 没有配置时退回按值
+        let unknown = "Decoration".to_string();
+        assert_eq!(config.lookup(2, Some(&unknown)), Some(by_value(2)));
+    }
+
+    #[test]
+    fn unconfigured_identifier_has_no_collider() {
+        let config = CollisionLayerConfig::default();
+        let decoration = "Decoration".to_string();
+        assert_eq!(config.lookup(7, Some(&decoration)), None);
+    }
+
+    #[test]
+    fn value_mapped_collider_properties() {
+        let mut app = tile_app();
+        let groups = CollisionGroups::new(Group::GROUP_2, Group::GROUP_1);
+        app.insert_resource(
+            CollisionLayerConfig::default().with_value(
+                3,
+                TileCollider::new(TileKind::Solid)
+                    .with_groups(groups)
+                    .with_sensor(true),
+            ),
+        );
+        let id = TileColliderId(
+            app.world
+                .resource::<CollisionLayerConfig>()
+                .lookup(3, None)
+                .unwrap(),
+        );
+        let (level, _) = spawn_level_with(&mut app, id, &[(0, 0), (1, 0)]);
+        app.update();
+
+        let centers = collider_centers(&mut app, level);
+        assert_eq!(centers.len(), 1);
+        let collider = app
+            .world
+            .entity(find_collider(&centers, Vec2::new(16., 8.)));
+        assert!(collider.contains::<Sensor>());
+        assert_eq!(collider.get::<CollisionGroups>(), Some(&groups));
+        assert!(!collider.contains::<Wall>());
+    }
+
+    #[test]
+    fn one_way_contact_states() {
+        use OneWayContact::*;
+        // 从上方落下的接触一直保留，接触消失后重新判断
+        assert_eq!(Unknown.next(true, true, true), (Allowed, true));
+        assert_eq!(Allowed.next(false, false, true), (Allowed, true));
+        assert_eq!(Allowed.next(true, true, false), (Unknown, true));
+        // 从下方进入时忽略，穿插期间法线转为朝上也不会弹回平台上
+        assert_eq!(Unknown.next(false, false, true), (Forbidden, false));
+        assert_eq!(Forbidden.next(true, false, true), (Forbidden, false));
+        assert_eq!(Forbidden.next(true, true, true), (Allowed, true));
+
+        for state in [Unknown, Allowed, Forbidden] {
+            assert_eq!(OneWayContact::from_user_data(state.user_data()), state);
+        }
     }
 }
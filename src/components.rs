@@ -3,12 +3,89 @@ use bevy::prelude::*;
 #[derive(Copy, Clone, Eq, PartialEq, Default, Debug, Component)]
 pub struct Player;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Component)]
-pub struct Grounded(pub bool);
+///
+/// 脚下的地面，由接触法线计算
+/// 悬空时 entity 为 None，normal 为 [Vec2::Y]
+///
+#[derive(Copy, Clone, PartialEq, Debug, Component)]
+pub struct Grounded {
+    pub entity: Option<Entity>,
+    pub normal: Vec2,
+}
 
 impl Default for Grounded {
     fn default() -> Self {
-        Self(true)
+        Self {
+            entity: None,
+            normal: Vec2::Y,
+        }
+    }
+}
+
+impl Grounded {
+    pub fn is_grounded(&self) -> bool {
+        self.entity.is_some()
+    }
+}
+
+///
+/// 输入产生的移动意图，由 [crate::player::MovementBackend] 执行
+///
+#[derive(Copy, Clone, PartialEq, Default, Debug, Component)]
+pub struct MoveIntent {
+    /// 期望的水平速度，为 0 时没有水平输入
+    pub horizontal: f32,
+    /// 这一帧起跳
+    pub jump: bool,
+    /// 这一帧的起跳是蹬墙跳
+    pub wall_jump: bool,
+    /// 这一帧松开跳跃键
+    pub jump_released: bool,
+    /// 冲刺中的水平速度，冲刺时忽略重力和其它水平输入
+    pub dash: Option<f32>,
+    /// 想要下蹲，头顶有墙时站不起来
+    pub crouch: bool,
+}
+
+///
+/// 朝向，由输入决定，不受物理推动影响
+///
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug, Component)]
+pub enum Facing {
+    Left,
+    #[default]
+    Right,
+}
+
+impl Facing {
+    /// 左为 -1，右为 1
+    pub fn direction(&self) -> f32 {
+        match self {
+            Facing::Left => -1.,
+            Facing::Right => 1.,
+        }
+    }
+}
+
+///
+/// 贴墙方向，没有贴墙时为 None
+///
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug, Component)]
+pub enum WallContact {
+    #[default]
+    None,
+    Left,
+    Right,
+}
+
+impl WallContact {
+    /// 墙所在方向，左为 -1，右为 1，没有贴墙为 0
+    pub fn direction(&self) -> f32 {
+        match self {
+            WallContact::None => 0.,
+            WallContact::Left => -1.,
+            WallContact::Right => 1.,
+        }
     }
 }
 
@@ -24,13 +101,31 @@ impl Default for JumpFlag {
     fn default() -> Self {
         Self {
             jump_count: 0,
-            // 三段跳，兄弟！
+            // 二段跳，兄弟！
             jump_limit: 2,
         }
     }
 }
 
 impl JumpFlag {
+    pub fn with_limit(jump_limit: usize) -> Self {
+        Self {
+            jump_limit,
+            ..default()
+        }
+    }
+
+    pub fn jump_limit(&self) -> usize {
+        self.jump_limit
+    }
+
+    ///
+    /// 道具等提高跳跃次数上限
+    ///
+    pub(crate) fn raise_limit(&mut self, amount: usize) {
+        self.jump_limit += amount;
+    }
+
     pub(crate) fn can_jump(&self) -> bool {
         self.jump_count < self.jump_limit
     }
@@ -43,3 +138,383 @@ impl JumpFlag {
         self.jump_count = 0;
     }
 }
+
+/// 受伤后无敌的时间
+const INVULNERABLE_SECONDS: f32 = 1.0;
+
+///
+/// 生命值，受伤后短暂无敌，避免一直站在尖刺上每帧掉血
+///
+#[derive(Copy, Clone, PartialEq, Debug, Component)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+    invulnerable: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            current: 3,
+            max: 3,
+            invulnerable: 0.,
+        }
+    }
+}
+
+impl Health {
+    pub(crate) fn is_dead(&self) -> bool {
+        self.current == 0
+    }
+
+    pub(crate) fn is_invulnerable(&self) -> bool {
+        self.invulnerable > 0.
+    }
+
+    ///
+    /// 扣血并进入无敌时间，无敌或已死亡时不生效
+    /// 返回是否受到伤害
+    ///
+    pub(crate) fn damage(&mut self, amount: u32) -> bool {
+        if self.is_invulnerable() || self.is_dead() {
+            return false;
+        }
+        self.current = self.current.saturating_sub(amount);
+        self.invulnerable = INVULNERABLE_SECONDS;
+        true
+    }
+
+    pub(crate) fn kill(&mut self) {
+        self.current = 0;
+    }
+
+    ///
+    /// 进入无敌时间，已经更长时不缩短
+    ///
+    pub(crate) fn grant_invulnerability(&mut self, seconds: f32) {
+        self.invulnerable = self.invulnerable.max(seconds);
+    }
+
+    pub(crate) fn revive(&mut self) {
+        self.current = self.max;
+        self.invulnerable = 0.;
+    }
+
+    pub(crate) fn tick(&mut self, delta: f32) {
+        self.invulnerable = (self.invulnerable - delta).max(0.);
+    }
+}
+
+///
+/// 冲刺状态，空中冲刺次数在落地时重置
+/// 按剩余距离而不是剩余时间结束，帧时间不均匀时冲刺距离也是固定的
+///
+#[derive(Copy, Clone, PartialEq, Debug, Component)]
+pub struct DashState {
+    // 空中冲刺次数
+    pub air_dash_count: usize,
+    // 空中冲刺次数限制
+    air_dash_limit: usize,
+    /// 还要冲刺的距离
+    distance_left: f32,
+    speed: f32,
+    cooldown: f32,
+    direction: f32,
+}
+
+impl Default for DashState {
+    fn default() -> Self {
+        Self {
+            air_dash_count: 0,
+            air_dash_limit: 1,
+            distance_left: 0.,
+            speed: 0.,
+            cooldown: 0.,
+            direction: 1.,
+        }
+    }
+}
+
+impl DashState {
+    pub fn is_dashing(&self) -> bool {
+        self.distance_left > 0.
+    }
+
+    /// 冲刺方向，左为 -1，右为 1
+    pub fn direction(&self) -> f32 {
+        self.direction
+    }
+
+    pub(crate) fn can_dash(&self, grounded: bool) -> bool {
+        !self.is_dashing()
+            && self.cooldown <= 0.
+            && (grounded || self.air_dash_count < self.air_dash_limit)
+    }
+
+    ///
+    /// 在 duration 秒内匀速冲刺 distance
+    ///
+    pub(crate) fn start(
+        &mut self,
+        direction: f32,
+        distance: f32,
+        duration: f32,
+        cooldown: f32,
+        grounded: bool,
+    ) {
+        self.direction = direction;
+        self.distance_left = distance;
+        self.speed = distance / duration;
+        self.cooldown = cooldown;
+        if !grounded {
+            self.air_dash_count += 1;
+        }
+    }
+
+    pub(crate) fn reset_air_dashes(&mut self) {
+        self.air_dash_count = 0;
+    }
+
+    ///
+    /// 复活时结束冲刺，清空冷却和空中冲刺次数
+    ///
+    pub(crate) fn cancel(&mut self) {
+        self.distance_left = 0.;
+        self.cooldown = 0.;
+        self.air_dash_count = 0;
+    }
+
+    ///
+    /// 冲刺前进 delta 秒，返回这一步的水平速度，不在冲刺时返回 None
+    /// delta 是玩家实际移动的时间，最后一步只走剩下的距离
+    ///
+    pub(crate) fn advance(&mut self, delta: f32) -> Option<f32> {
+        if !self.is_dashing() {
+            return None;
+        }
+        if delta <= 0. {
+            return Some(self.direction * self.speed);
+        }
+        let step = (self.speed * delta).min(self.distance_left);
+        self.distance_left -= step;
+        Some(self.direction * step / delta)
+    }
+
+    pub(crate) fn tick(&mut self, delta: f32) {
+        self.cooldown = (self.cooldown - delta).max(0.);
+    }
+}
+
+/// 耗尽后恢复到上限的这个比例才能再次冲刺跑
+const STAMINA_RECOVER_RATIO: f32 = 0.25;
+
+///
+/// 体力，冲刺跑时消耗，不跑时恢复
+/// 耗尽后进入疲劳，恢复到一定比例或松开 Sprint 前不能再消耗
+///
+#[derive(Copy, Clone, PartialEq, Debug, Component)]
+pub struct Stamina {
+    pub current: f32,
+    pub max: f32,
+    exhausted: bool,
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self {
+            current: 1.,
+            max: 1.,
+            exhausted: false,
+        }
+    }
+}
+
+impl Stamina {
+    ///
+    /// 消耗体力，不够时不消耗并进入疲劳
+    /// 返回是否消耗成功
+    ///
+    pub(crate) fn drain(&mut self, amount: f32) -> bool {
+        if amount <= 0. {
+            return true;
+        }
+        if self.exhausted {
+            return false;
+        }
+        if self.current < amount {
+            self.exhausted = true;
+            return false;
+        }
+        self.current -= amount;
+        true
+    }
+
+    pub(crate) fn regenerate(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+        if self.current >= self.max * STAMINA_RECOVER_RATIO {
+            self.exhausted = false;
+        }
+    }
+
+    ///
+    /// 松开 Sprint 时解除疲劳，体力够的话下次按下可以直接跑
+    ///
+    pub(crate) fn release(&mut self) {
+        self.exhausted = false;
+    }
+}
+
+///
+/// 下蹲状态，下蹲时 collider 变矮
+///
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug, Component)]
+pub struct Crouch {
+    pub crouching: bool,
+}
+
+///
+/// 按住 Up/Down 一段时间后镜头上下看的偏移
+///
+#[derive(Copy, Clone, PartialEq, Default, Debug, Component)]
+pub struct LookAhead {
+    held: f32,
+    pub offset: f32,
+}
+
+impl LookAhead {
+    ///
+    /// direction 为 1 向上看，-1 向下看，0 回正
+    /// 按住超过 delay 后才开始移动镜头
+    ///
+    pub(crate) fn update(
+        &mut self,
+        direction: f32,
+        delay: f32,
+        distance: f32,
+        speed: f32,
+        delta: f32,
+    ) {
+        if direction == 0. {
+            self.held = 0.;
+        } else {
+            self.held += delta;
+        }
+        let target = if self.held >= delay {
+            direction * distance
+        } else {
+            0.
+        };
+        let step = speed * delta;
+        self.offset = if self.offset < target {
+            (self.offset + step).min(target)
+        } else {
+            (self.offset - step).max(target)
+        };
+    }
+}
+
+/// 穿过单向平台持续的时间
+const DROP_THROUGH_SECONDS: f32 = 0.25;
+/// 站在单向平台上按住 Down 多久后穿过去，短按只下蹲
+const DROP_HOLD_SECONDS: f32 = 0.2;
+
+///
+/// 穿过单向平台，计时期间忽略与单向平台的接触
+/// 计时结束时还穿插在平台里的接触会继续被忽略，直到离开平台
+///
+#[derive(Copy, Clone, PartialEq, Default, Debug, Component)]
+pub struct DropThrough {
+    remaining: f32,
+    held: f32,
+}
+
+impl DropThrough {
+    pub(crate) fn start(&mut self) {
+        self.remaining = DROP_THROUGH_SECONDS;
+    }
+
+    ///
+    /// 站在单向平台上按住 Down 时计时，超过 [DROP_HOLD_SECONDS] 开始穿过
+    /// 返回这一帧是否开始穿过
+    ///
+    pub(crate) fn hold(&mut self, holding: bool, delta: f32) -> bool {
+        if !holding {
+            self.held = 0.;
+            return false;
+        }
+        self.held += delta;
+        if self.held < DROP_HOLD_SECONDS || self.is_active() {
+            return false;
+        }
+        self.held = 0.;
+        self.start();
+        true
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.remaining > 0.
+    }
+
+    pub(crate) fn tick(&mut self, delta: f32) {
+        self.remaining = (self.remaining - delta).max(0.);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exhausted_stamina_waits_for_recovery() {
+        let mut stamina = Stamina::default();
+        while stamina.drain(0.1) {}
+
+        // 恢复一点点不足以再次冲刺跑
+        stamina.regenerate(0.1);
+        assert!(!stamina.drain(0.01));
+        stamina.regenerate(0.2);
+        assert!(stamina.drain(0.01));
+    }
+
+    #[test]
+    fn releasing_sprint_clears_exhaustion() {
+        let mut stamina = Stamina::default();
+        while stamina.drain(0.3) {}
+        // 还剩 0.1，但已经疲劳
+        assert!(!stamina.drain(0.05));
+        stamina.release();
+        assert!(stamina.drain(0.05));
+    }
+
+    #[test]
+    fn dash_covers_fixed_distance_with_uneven_deltas() {
+        for direction in [1., -1.] {
+            let mut dash = DashState::default();
+            dash.start(direction, 48., 0.15, 0.5, true);
+            let deltas = [0.04, 0.01, 0.025, 0.04, 0.04, 0.04];
+            let mut distance = 0.;
+            for delta in deltas {
+                if let Some(speed) = dash.advance(delta) {
+                    distance += speed * delta;
+                }
+            }
+            assert!(!dash.is_dashing());
+            assert!((distance - direction * 48.).abs() < 1e-3, "{}", distance);
+        }
+    }
+
+    #[test]
+    fn holding_down_on_platform_starts_drop_through() {
+        let mut drop_through = DropThrough::default();
+        // 短按 Down 只下蹲，松开后重新计时
+        assert!(!drop_through.hold(true, 0.1));
+        assert!(!drop_through.hold(false, 0.1));
+        assert!(!drop_through.hold(true, 0.1));
+        assert!(!drop_through.is_active());
+
+        assert!(drop_through.hold(true, 0.1));
+        assert!(drop_through.is_active());
+        // 穿过期间继续按住不会重新开始
+        assert!(!drop_through.hold(true, 0.2));
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+use crate::animation::{AnimationFinished, AnimationSet, AnimationState, AnimationStateMachine};
+use crate::components::Player;
+
+///
+/// 特效插件
+/// 发送 [SpawnEffect] 事件即可在指定位置播放一次性特效，播完自动 despawn
+///
+pub struct EffectPlugin;
+
+impl Plugin for EffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EffectResource>()
+            .add_event::<SpawnEffect>()
+            .add_system(appear_on_player_spawn)
+            .add_system(spawn_effect)
+            .add_system(despawn_finished_effect);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Appearing,
+    Desappearing,
+}
+
+impl Effect {
+    fn state(&self) -> AnimationState {
+        match self {
+            Effect::Appearing => AnimationState::Appearing,
+            Effect::Desappearing => AnimationState::Desappearing,
+        }
+    }
+}
+
+///
+/// 播放特效
+/// parent 为 Some 时特效作为其子 entity，translation 为相对位置
+///
+#[derive(Debug, Clone)]
+pub struct SpawnEffect {
+    pub effect: Effect,
+    pub translation: Vec3,
+    pub parent: Option<Entity>,
+}
+
+impl SpawnEffect {
+    pub fn at(effect: Effect, translation: Vec3) -> Self {
+        Self {
+            effect,
+            translation,
+            parent: None,
+        }
+    }
+
+    pub fn attached(effect: Effect, parent: Entity) -> Self {
+        Self {
+            effect,
+            // 画在 parent 上面
+            translation: Vec3::Z,
+            parent: Some(parent),
+        }
+    }
+}
+
+///
+/// 特效 entity 标记，动画播完后 despawn
+///
+#[derive(Debug, Clone, Copy, Default, Component)]
+struct EffectAnimation;
+
+///
+/// 提前加载特效动画清单，避免第一次播放时还在加载
+///
+#[derive(Debug, Resource)]
+struct EffectResource {
+    set: Handle<AnimationSet>,
+}
+
+impl FromWorld for EffectResource {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        EffectResource {
+            set: asset_server.load("animations/effects.anim.ron"),
+        }
+    }
+}
+
+fn spawn_effect(
+    mut commands: Commands,
+    mut events: EventReader<SpawnEffect>,
+    effects: Res<EffectResource>,
+) {
+    for event in events.iter() {
+        let effect = commands
+            .spawn((
+                SpriteSheetBundle {
+                    transform: Transform::from_translation(event.translation),
+                    ..default()
+                },
+                effects.set.clone(),
+                AnimationStateMachine::new(event.effect.state()),
+                EffectAnimation,
+            ))
+            .id();
+        if let Some(parent) = event.parent {
+            commands.entity(parent).add_child(effect);
+        }
+    }
+}
+
+fn despawn_finished_effect(
+    mut commands: Commands,
+    mut finished: EventReader<AnimationFinished>,
+    query: Query<(), With<EffectAnimation>>,
+) {
+    for event in finished.iter() {
+        if query.contains(event.entity) {
+            commands.entity(event.entity).despawn_recursive();
+        }
+    }
+}
+
+///
+/// 玩家从 Ldtk `Player` entity 生成时播放出场特效
+///
+fn appear_on_player_spawn(
+    query: Query<Entity, Added<Player>>,
+    mut effects: EventWriter<SpawnEffect>,
+) {
+    for entity in &query {
+        effects.send(SpawnEffect::attached(Effect::Appearing, entity));
+    }
+}
@@ -0,0 +1,730 @@
+use bevy::{
+    prelude::IVec2,
+    utils::{HashMap, HashSet},
+};
+use bevy_ecs_ldtk::prelude::GridCoords;
+
+///
+/// 一个由 cell 组成的矩形，四条边都是包含的 cell 坐标
+///
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct Rect {
+    pub left: i32,
+    pub right: i32,
+    pub top: i32,
+    pub bottom: i32,
+}
+
+impl Rect {
+    pub fn width(&self) -> i32 {
+        self.right - self.left + 1
+    }
+
+    pub fn height(&self) -> i32 {
+        self.top - self.bottom + 1
+    }
+}
+
+/// Represents a wide wall that is 1 tile tall
+/// Used to spawn wall collisions
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+struct Plate {
+    left: i32,
+    right: i32,
+}
+
+///
+/// 把任意位置的一组 cell 合并成矩形
+/// 先平移到包围盒内再调用 [merge_grid_rects]
+///
+pub fn merge_region_rects(cells: &HashSet<GridCoords>) -> Vec<Rect> {
+    let Some(min_x) = cells.iter().map(|c| c.x).min() else {
+        return Vec::new();
+    };
+    let min_y = cells.iter().map(|c| c.y).min().unwrap_or_default();
+    let max_x = cells.iter().map(|c| c.x).max().unwrap_or_default();
+    let max_y = cells.iter().map(|c| c.y).max().unwrap_or_default();
+
+    let local: HashSet<GridCoords> = cells
+        .iter()
+        .map(|c| GridCoords {
+            x: c.x - min_x,
+            y: c.y - min_y,
+        })
+        .collect();
+
+    merge_grid_rects(&local, max_x - min_x + 1, max_y - min_y + 1)
+        .into_iter()
+        .map(|rect| Rect {
+            left: rect.left + min_x,
+            right: rect.right + min_x,
+            top: rect.top + min_y,
+            bottom: rect.bottom + min_y,
+        })
+        .collect()
+}
+
+///
+/// 按上下左右相邻把 cell 分成若干连通区域
+///
+pub fn connected_regions(cells: &HashSet<GridCoords>) -> Vec<HashSet<GridCoords>> {
+    let mut visited: HashSet<GridCoords> = HashSet::new();
+    let mut regions = Vec::new();
+
+    for &start in cells {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut region = HashSet::new();
+        let mut stack = vec![start];
+        while let Some(coords) = stack.pop() {
+            region.insert(coords);
+            for neighbor in neighbors(coords) {
+                if cells.contains(&neighbor) && visited.insert(neighbor) {
+                    stack.push(neighbor);
+                }
+            }
+        }
+        regions.push(region);
+    }
+    regions
+}
+
+///
+/// 上下左右四个相邻 cell
+///
+pub fn neighbors(coords: GridCoords) -> [GridCoords; 4] {
+    [
+        GridCoords {
+            x: coords.x - 1,
+            y: coords.y,
+        },
+        GridCoords {
+            x: coords.x + 1,
+            y: coords.y,
+        },
+        GridCoords {
+            x: coords.x,
+            y: coords.y - 1,
+        },
+        GridCoords {
+            x: coords.x,
+            y: coords.y + 1,
+        },
+    ]
+}
+
+///
+/// ref: https://github.com/Trouv/bevy_ecs_ldtk
+/// 把 `width` x `height` 范围内的 cell 合并成互不重叠的矩形
+/// 先按行合并成 plate，再把上下相同的 plate 合并成矩形
+///
+pub fn merge_grid_rects(cells: &HashSet<GridCoords>, width: i32, height: i32) -> Vec<Rect> {
+    // combine wall tiles into flat "plates" in each individual row
+    let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
+
+    for y in 0..height {
+        let mut row_plates: Vec<Plate> = Vec::new();
+        let mut plate_start = None;
+
+        // + 1 to the width so the algorithm "terminates" plates that touch the right edge
+        for x in 0..width + 1 {
+            match (plate_start, cells.contains(&GridCoords { x, y })) {
+                (Some(s), false) => {
+                    row_plates.push(Plate {
+                        left: s,
+                        right: x - 1,
+                    });
+                    plate_start = None;
+                }
+                (None, true) => plate_start = Some(x),
+                _ => (),
+            }
+        }
+
+        plate_stack.push(row_plates);
+    }
+
+    // combine "plates" into rectangles across multiple rows
+    let mut rect_builder: HashMap<Plate, Rect> = HashMap::new();
+    let mut prev_row: Vec<Plate> = Vec::new();
+    let mut rects: Vec<Rect> = Vec::new();
+
+    // an extra empty row so the algorithm "finishes" the rects that touch the top edge
+    plate_stack.push(Vec::new());
+
+    for (y, current_row) in plate_stack.into_iter().enumerate() {
+        for prev_plate in &prev_row {
+            if !current_row.contains(prev_plate) {
+                // remove the finished rect so that the same plate in the future starts a new rect
+                if let Some(rect) = rect_builder.remove(prev_plate) {
+                    rects.push(rect);
+                }
+            }
+        }
+        for plate in &current_row {
+            rect_builder
+                .entry(plate.clone())
+                .and_modify(|e| e.top += 1)
+                .or_insert(Rect {
+                    bottom: y as i32,
+                    top: y as i32,
+                    left: plate.left,
+                    right: plate.right,
+                });
+        }
+        prev_row = current_row;
+    }
+
+    rects
+}
+
+///
+/// 把任意位置的一组 cell 划分成数量最少的矩形
+///
+/// 凹顶点之间的水平、竖直弦两两相交时只能选一条，
+/// 用二分图最大匹配求出互不相交的最多的弦，沿这些弦切开，
+/// 剩下的凹顶点各自竖直切一刀，切到边界或已有的切线为止
+/// 比 [merge_region_rects] 慢，结果的矩形数量最少
+///
+pub fn partition_region_rects(cells: &HashSet<GridCoords>) -> Vec<Rect> {
+    let filled = |x: i32, y: i32| cells.contains(&GridCoords { x, y });
+    // 从 point 出发沿 dir 的一条单位边两侧都是实心 cell
+    let interior = |point: IVec2, dir: IVec2| {
+        let (a, b) = match (dir.x, dir.y) {
+            (1, 0) => ((point.x, point.y - 1), (point.x, point.y)),
+            (-1, 0) => ((point.x - 1, point.y - 1), (point.x - 1, point.y)),
+            (0, 1) => ((point.x - 1, point.y), (point.x, point.y)),
+            _ => ((point.x - 1, point.y - 1), (point.x, point.y - 1)),
+        };
+        filled(a.0, a.1) && filled(b.0, b.1)
+    };
+
+    // 凹顶点：周围四个 cell 有三个是实心
+    let mut concave: Vec<IVec2> = cells
+        .iter()
+        .flat_map(|c| {
+            [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| IVec2::new(c.x + dx, c.y + dy))
+        })
+        .collect::<HashSet<IVec2>>()
+        .into_iter()
+        .filter(|p| {
+            [(-1, -1), (0, -1), (-1, 0), (0, 0)]
+                .iter()
+                .filter(|(dx, dy)| filled(p.x + dx, p.y + dy))
+                .count()
+                == 3
+        })
+        .collect();
+    concave.sort_by_key(|p| (p.y, p.x));
+    let concave_set: HashSet<IVec2> = concave.iter().copied().collect();
+
+    // 两个凹顶点之间完全在内部的弦，只从左端、下端出发找，避免重复
+    let chords = |dir: IVec2| -> Vec<(IVec2, IVec2)> {
+        concave
+            .iter()
+            .filter_map(|&start| {
+                let mut point = start;
+                while interior(point, dir) {
+                    point += dir;
+                    if concave_set.contains(&point) {
+                        return Some((start, point));
+                    }
+                }
+                None
+            })
+            .collect()
+    };
+    let horizontal = chords(IVec2::X);
+    let vertical = chords(IVec2::Y);
+
+    // 水平弦和竖直弦相交（包括共用端点）时连一条边
+    let adjacency: Vec<Vec<usize>> = horizontal
+        .iter()
+        .map(|(h_start, h_end)| {
+            vertical
+                .iter()
+                .enumerate()
+                .filter(|(_, (v_start, v_end))| {
+                    (h_start.x..=h_end.x).contains(&v_start.x)
+                        && (v_start.y..=v_end.y).contains(&h_start.y)
+                })
+                .map(|(index, _)| index)
+                .collect()
+        })
+        .collect();
+    let (keep_horizontal, keep_vertical) = max_independent_set(&adjacency, vertical.len());
+
+    // 切线按单位边记录：水平边用左端点，竖直边用下端点
+    let mut h_cuts: HashSet<IVec2> = HashSet::new();
+    let mut v_cuts: HashSet<IVec2> = HashSet::new();
+    let mut touched: HashSet<IVec2> = HashSet::new();
+    let mut cut = |from: IVec2, dir: IVec2| {
+        let edge = if dir.x + dir.y > 0 { from } else { from + dir };
+        if dir.y == 0 {
+            h_cuts.insert(edge);
+        } else {
+            v_cuts.insert(edge);
+        }
+    };
+    let chosen = horizontal
+        .iter()
+        .zip(keep_horizontal)
+        .map(|(chord, keep)| (chord, keep, IVec2::X))
+        .chain(
+            vertical
+                .iter()
+                .zip(keep_vertical)
+                .map(|(chord, keep)| (chord, keep, IVec2::Y)),
+        )
+        .filter(|(_, keep, _)| *keep);
+    for (&(start, end), _, dir) in chosen {
+        let mut point = start;
+        touched.insert(point);
+        while point != end {
+            cut(point, dir);
+            point += dir;
+            touched.insert(point);
+        }
+    }
+
+    // 没有被弦切到的凹顶点，沿内部的竖直方向切到边界或已有的切线
+    for &start in &concave {
+        if touched.contains(&start) {
+            continue;
+        }
+        let dir = if interior(start, IVec2::Y) {
+            IVec2::Y
+        } else {
+            IVec2::NEG_Y
+        };
+        touched.insert(start);
+        let mut point = start;
+        while interior(point, dir) {
+            cut(point, dir);
+            point += dir;
+            if !touched.insert(point) {
+                break;
+            }
+        }
+    }
+
+    // 不跨过切线的连通块都是矩形
+    let mut visited: HashSet<GridCoords> = HashSet::new();
+    let mut rects = Vec::new();
+    let mut sorted: Vec<GridCoords> = cells.iter().copied().collect();
+    sorted.sort_by_key(|c| (c.y, c.x));
+    for start in sorted {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut rect = Rect {
+            left: start.x,
+            right: start.x,
+            top: start.y,
+            bottom: start.y,
+        };
+        let mut stack = vec![start];
+        while let Some(GridCoords { x, y }) = stack.pop() {
+            rect.left = rect.left.min(x);
+            rect.right = rect.right.max(x);
+            rect.bottom = rect.bottom.min(y);
+            rect.top = rect.top.max(y);
+            let steps = [
+                (
+                    GridCoords { x: x - 1, y },
+                    v_cuts.contains(&IVec2::new(x, y)),
+                ),
+                (
+                    GridCoords { x: x + 1, y },
+                    v_cuts.contains(&IVec2::new(x + 1, y)),
+                ),
+                (
+                    GridCoords { x, y: y - 1 },
+                    h_cuts.contains(&IVec2::new(x, y)),
+                ),
+                (
+                    GridCoords { x, y: y + 1 },
+                    h_cuts.contains(&IVec2::new(x, y + 1)),
+                ),
+            ];
+            for (next, blocked) in steps {
+                if !blocked && cells.contains(&next) && visited.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        rects.push(rect);
+    }
+    rects
+}
+
+///
+/// 二分图的最大独立集，adjacency 是左侧每个点相邻的右侧点
+/// 先求最大匹配，再由 König 定理从最小点覆盖取补集
+/// 返回左侧、右侧每个点是否在独立集中
+///
+fn max_independent_set(adjacency: &[Vec<usize>], right_len: usize) -> (Vec<bool>, Vec<bool>) {
+    fn augment(
+        left: usize,
+        adjacency: &[Vec<usize>],
+        seen: &mut [bool],
+        matched_left: &mut [Option<usize>],
+    ) -> bool {
+        for &right in &adjacency[left] {
+            if seen[right] {
+                continue;
+            }
+            seen[right] = true;
+            let free = match matched_left[right] {
+                None => true,
+                Some(other) => augment(other, adjacency, seen, matched_left),
+            };
+            if free {
+                matched_left[right] = Some(left);
+                return true;
+            }
+        }
+        false
+    }
+
+    // matched_left[right]：和右侧点匹配的左侧点
+    let mut matched_left: Vec<Option<usize>> = vec![None; right_len];
+    let matched: Vec<bool> = (0..adjacency.len())
+        .map(|left| {
+            let mut seen = vec![false; right_len];
+            augment(left, adjacency, &mut seen, &mut matched_left)
+        })
+        .collect();
+
+    // 从未匹配的左侧点出发走交错路径
+    let mut left_visited = vec![false; adjacency.len()];
+    let mut right_visited = vec![false; right_len];
+    let mut stack: Vec<usize> = (0..adjacency.len()).filter(|&l| !matched[l]).collect();
+    for &left in &stack {
+        left_visited[left] = true;
+    }
+    while let Some(left) = stack.pop() {
+        for &right in &adjacency[left] {
+            if right_visited[right] {
+                continue;
+            }
+            right_visited[right] = true;
+            if let Some(next) = matched_left[right] {
+                if !left_visited[next] {
+                    left_visited[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+    }
+
+    // 最小点覆盖是未访问的左侧点和访问过的右侧点，独立集取补集
+    let right_keep = right_visited.into_iter().map(|visited| !visited).collect();
+    (left_visited, right_keep)
+}
+
+///
+/// 描出 cell 区域的轮廓，返回若干闭合折线，顶点为 cell 角点的网格坐标
+/// 外轮廓逆时针，洞顺时针；只有对角相接的 cell 属于不同的轮廓
+/// 同一直线上的中间顶点会被去掉
+///
+pub fn trace_outlines(cells: &HashSet<GridCoords>) -> Vec<Vec<IVec2>> {
+    // 每条边界边：起点 -> 终点，实心在前进方向左侧
+    let mut edges: HashMap<IVec2, Vec<IVec2>> = HashMap::new();
+    for &GridCoords { x, y } in cells {
+        let has = |dx: i32, dy: i32| {
+            cells.contains(&GridCoords {
+                x: x + dx,
+                y: y + dy,
+            })
+        };
+        let mut add = |from: (i32, i32), to: (i32, i32)| {
+            edges
+                .entry(IVec2::new(from.0, from.1))
+                .or_default()
+                .push(IVec2::new(to.0, to.1));
+        };
+        if !has(0, -1) {
+            add((x, y), (x + 1, y));
+        }
+        if !has(1, 0) {
+            add((x + 1, y), (x + 1, y + 1));
+        }
+        if !has(0, 1) {
+            add((x + 1, y + 1), (x, y + 1));
+        }
+        if !has(-1, 0) {
+            add((x, y + 1), (x, y));
+        }
+    }
+
+    // 起点排序，保证结果稳定
+    let mut starts: Vec<IVec2> = edges.keys().copied().collect();
+    starts.sort_by_key(|v| (v.y, v.x));
+
+    let mut outlines = Vec::new();
+    for start in starts {
+        while let Some(first) = take_edge(&mut edges, start, None) {
+            if let Some(points) = follow_outline(&mut edges, start, first) {
+                outlines.push(remove_collinear(points));
+            }
+        }
+    }
+    outlines
+}
+
+///
+/// 从 `start` 出发沿边界边走回 `start`，返回经过的顶点
+/// cell 的边界边总是首尾相连的，走不回起点时丢弃这段轮廓，返回 None
+///
+fn follow_outline(
+    edges: &mut HashMap<IVec2, Vec<IVec2>>,
+    start: IVec2,
+    first: IVec2,
+) -> Option<Vec<IVec2>> {
+    let mut points = vec![start];
+    let mut prev = start;
+    let mut current = first;
+    while current != start {
+        points.push(current);
+        let next = take_edge(edges, current, Some(current - prev))?;
+        prev = current;
+        current = next;
+    }
+    Some(points)
+}
+
+///
+/// 取出一条从 `from` 出发的边
+/// 两个区域对角相接时一个点有两条出边，优先左转，其次直行、右转，
+/// 这样轮廓始终贴着当前区域走
+///
+fn take_edge(
+    edges: &mut HashMap<IVec2, Vec<IVec2>>,
+    from: IVec2,
+    incoming: Option<IVec2>,
+) -> Option<IVec2> {
+    let outgoing = edges.get_mut(&from)?;
+    let index = match incoming {
+        Some(dir) => {
+            let left = IVec2::new(-dir.y, dir.x);
+            [left, dir, -left]
+                .iter()
+                .find_map(|want| outgoing.iter().position(|to| *to - from == *want))?
+        }
+        None => 0,
+    };
+    let to = outgoing.swap_remove(index);
+    if outgoing.is_empty() {
+        edges.remove(&from);
+    }
+    Some(to)
+}
+
+fn remove_collinear(points: Vec<IVec2>) -> Vec<IVec2> {
+    let len = points.len();
+    (0..len)
+        .filter(|&i| {
+            let prev = points[(i + len - 1) % len];
+            let next = points[(i + 1) % len];
+            points[i] - prev != next - points[i]
+        })
+        .map(|i| points[i])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn cells(coords: &[(i32, i32)]) -> HashSet<GridCoords> {
+        coords.iter().map(|&(x, y)| GridCoords { x, y }).collect()
+    }
+
+    fn cells_strategy(size: i32) -> impl Strategy<Value = HashSet<GridCoords>> {
+        prop::collection::vec((0..size, 0..size), 0..(size * size) as usize)
+            .prop_map(|coords| cells(&coords))
+    }
+
+    ///
+    /// 矩形覆盖的 cell 正好是输入，且矩形之间没有重叠
+    ///
+    fn assert_exact_cover(rects: &[Rect], input: &HashSet<GridCoords>) {
+        let mut covered = HashSet::new();
+        for rect in rects {
+            assert!(rect.width() > 0 && rect.height() > 0, "{:?}", rect);
+            for x in rect.left..=rect.right {
+                for y in rect.bottom..=rect.top {
+                    let coords = GridCoords { x, y };
+                    assert!(input.contains(&coords), "{:?} outside input", coords);
+                    assert!(covered.insert(coords), "{:?} covered twice", coords);
+                }
+            }
+        }
+        assert_eq!(covered.len(), input.len());
+    }
+
+    /// 鞋带公式，逆时针为正
+    fn signed_area(outline: &[IVec2]) -> i32 {
+        let len = outline.len();
+        (0..len)
+            .map(|i| {
+                let (a, b) = (outline[i], outline[(i + 1) % len]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum::<i32>()
+            / 2
+    }
+
+    #[test]
+    fn merge_grid_rects_merges_rows_into_one_rect() {
+        let input = cells(&[(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+        let rects = merge_grid_rects(&input, 3, 2);
+        assert_eq!(
+            rects,
+            vec![Rect {
+                left: 0,
+                right: 2,
+                top: 1,
+                bottom: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn merge_region_rects_keeps_offset() {
+        let input = cells(&[(-3, 5), (-2, 5)]);
+        let rects = merge_region_rects(&input);
+        assert_eq!(
+            rects,
+            vec![Rect {
+                left: -3,
+                right: -2,
+                top: 5,
+                bottom: 5,
+            }]
+        );
+        assert!(merge_region_rects(&HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn trace_outlines_single_cell_is_counter_clockwise_square() {
+        let outlines = trace_outlines(&cells(&[(0, 0)]));
+        assert_eq!(
+            outlines,
+            vec![vec![
+                IVec2::new(0, 0),
+                IVec2::new(1, 0),
+                IVec2::new(1, 1),
+                IVec2::new(0, 1),
+            ]]
+        );
+    }
+
+    #[test]
+    fn trace_outlines_ring_has_clockwise_hole() {
+        let ring: Vec<(i32, i32)> = (0..3)
+            .flat_map(|x| (0..3).map(move |y| (x, y)))
+            .filter(|&coords| coords != (1, 1))
+            .collect();
+        let outlines = trace_outlines(&cells(&ring));
+        assert_eq!(outlines.len(), 2);
+        let mut areas: Vec<i32> = outlines.iter().map(|o| signed_area(o)).collect();
+        areas.sort();
+        assert_eq!(areas, vec![-1, 9]);
+    }
+
+    #[test]
+    fn trace_outlines_splits_diagonal_cells() {
+        let outlines = trace_outlines(&cells(&[(0, 0), (1, 1)]));
+        assert_eq!(outlines.len(), 2);
+        assert!(outlines.iter().all(|outline| outline.len() == 4));
+        assert!(outlines.iter().all(|outline| signed_area(outline) == 1));
+    }
+
+    #[test]
+    fn connected_regions_splits_by_edge_adjacency() {
+        let input = cells(&[(0, 0), (1, 0), (3, 0), (1, 1), (2, 2)]);
+        let mut sizes: Vec<usize> = connected_regions(&input).iter().map(|r| r.len()).collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 1, 3]);
+    }
+
+    #[test]
+    fn partition_region_rects_beats_greedy_merge() {
+        // 一列三个 cell，中间右侧再接一个
+        let input = cells(&[(0, 0), (0, 1), (0, 2), (1, 1)]);
+        assert_eq!(merge_region_rects(&input).len(), 3);
+        let rects = partition_region_rects(&input);
+        assert_exact_cover(&rects, &input);
+        assert_eq!(rects.len(), 2);
+    }
+
+    #[test]
+    fn partition_region_rects_ring_needs_four() {
+        let ring: Vec<(i32, i32)> = (0..3)
+            .flat_map(|x| (0..3).map(move |y| (x, y)))
+            .filter(|&coords| coords != (1, 1))
+            .collect();
+        let input = cells(&ring);
+        let rects = partition_region_rects(&input);
+        assert_exact_cover(&rects, &input);
+        assert_eq!(rects.len(), 4);
+    }
+
+    proptest! {
+        #[test]
+        fn partition_region_rects_covers_input_exactly(input in cells_strategy(12)) {
+            let rects = partition_region_rects(&input);
+            assert_exact_cover(&rects, &input);
+            prop_assert!(rects.len() <= merge_region_rects(&input).len());
+        }
+
+        #[test]
+        fn merge_grid_rects_covers_input_exactly(input in cells_strategy(12)) {
+            assert_exact_cover(&merge_grid_rects(&input, 12, 12), &input);
+        }
+
+        #[test]
+        fn merge_region_rects_covers_input_exactly(
+            input in cells_strategy(12),
+            dx in -40..40,
+            dy in -40..40,
+        ) {
+            let input: HashSet<GridCoords> = input
+                .into_iter()
+                .map(|c| GridCoords { x: c.x + dx, y: c.y + dy })
+                .collect();
+            assert_exact_cover(&merge_region_rects(&input), &input);
+        }
+
+        #[test]
+        fn connected_regions_partition_input(input in cells_strategy(12)) {
+            let regions = connected_regions(&input);
+            let total: usize = regions.iter().map(|r| r.len()).sum();
+            prop_assert_eq!(total, input.len());
+            let union: HashSet<GridCoords> = regions.into_iter().flatten().collect();
+            prop_assert_eq!(union, input);
+        }
+
+        #[test]
+        fn trace_outlines_enclose_input_area(input in cells_strategy(12)) {
+            let outlines = trace_outlines(&input);
+            for outline in &outlines {
+                prop_assert!(outline.len() >= 4);
+                // 去掉共线点后相邻两条边都是转角
+                let len = outline.len();
+                for i in 0..len {
+                    let prev = outline[(i + len - 1) % len];
+                    let next = outline[(i + 1) % len];
+                    prop_assert_ne!(outline[i] - prev, next - outline[i]);
+                }
+            }
+            let area: i32 = outlines.iter().map(|o| signed_area(o)).sum();
+            prop_assert_eq!(area, input.len() as i32);
+        }
+    }
+}
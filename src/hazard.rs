@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::animation::{AnimationState, AnimationStateMachine};
+use crate::collision::Hazard;
+use crate::components::{Health, Player};
+
+///
+/// 危险区域插件
+/// 玩家碰到 [Hazard] sensor 时扣血，发送 [PlayerHurt] / [PlayerKilled] 事件
+///
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerHurt>()
+            .add_event::<PlayerKilled>()
+            .add_system(hazard_detection)
+            .add_system(tick_invulnerability)
+            .add_system(play_hit_animation);
+    }
+}
+
+/// 受伤动画的优先级，高于所有移动状态
+const HIT_PRIORITY: u8 = 4;
+
+///
+/// 玩家受到伤害
+///
+#[derive(Debug, Clone)]
+pub struct PlayerHurt {
+    pub entity: Entity,
+    pub damage: u32,
+}
+
+///
+/// 玩家死亡，生命值归零或碰到 [Hazard::Kill]
+///
+#[derive(Debug, Clone)]
+pub struct PlayerKilled {
+    pub entity: Entity,
+}
+
+fn hazard_detection(
+    mut player_query: Query<(Entity, &mut Health), With<Player>>,
+    hazard_query: Query<&Hazard>,
+    rapier_context: Res<RapierContext>,
+    mut hurt_events: EventWriter<PlayerHurt>,
+    mut killed_events: EventWriter<PlayerKilled>,
+) {
+    for (entity, mut health) in &mut player_query {
+        if health.is_dead() {
+            continue;
+        }
+        for (collider1, collider2, intersecting) in rapier_context.intersections_with(entity) {
+            if !intersecting {
+                continue;
+            }
+            let other = if collider1 == entity {
+                collider2
+            } else {
+                collider1
+            };
+            let Ok(hazard) = hazard_query.get(other) else {
+                continue;
+            };
+
+            match *hazard {
+                Hazard::Damage(damage) => {
+                    if health.damage(damage) {
+                        hurt_events.send(PlayerHurt { entity, damage });
+                    }
+                }
+                Hazard::Kill => health.kill(),
+            }
+            if health.is_dead() {
+                killed_events.send(PlayerKilled { entity });
+                break;
+            }
+        }
+    }
+}
+
+fn tick_invulnerability(mut query: Query<&mut Health>, time: Res<Time>) {
+    for mut health in &mut query {
+        if health.is_invulnerable() {
+            health.tick(time.delta_seconds());
+        }
+    }
+}
+
+fn play_hit_animation(
+    mut hurt_events: EventReader<PlayerHurt>,
+    mut query: Query<&mut AnimationStateMachine>,
+) {
+    for event in hurt_events.iter() {
+        debug!("{:?} took {} damage", event.entity, event.damage);
+        if let Ok(mut state_machine) = query.get_mut(event.entity) {
+            state_machine.play_once(AnimationState::Hit, HIT_PRIORITY);
+        }
+    }
+}
@@ -4,15 +4,24 @@ use bevy_inspector_egui_rapier::InspectableRapierPlugin;
 use bevy_rapier2d::prelude::*;
 
 mod animation;
+mod checkpoint;
 mod collision;
 mod components;
+mod effect;
+mod grid;
+mod hazard;
 mod player;
+mod powerup;
 mod wasd;
 
 use animation::*;
+use checkpoint::*;
 use collision::*;
-use components::Player;
+use components::{LookAhead, Player};
+use effect::*;
+use hazard::*;
 use player::*;
+use powerup::*;
 use wasd::*;
 
 fn main() {
@@ -26,11 +35,18 @@ fn main() {
         .add_plugin(InspectableRapierPlugin)
         // 游戏逻辑插件
         .add_plugin(AnimationPlugin)
+        .add_plugin(CheckpointPlugin)
+        .add_plugin(EffectPlugin)
         .add_plugin(FixedBlockCollisionPlugin)
-        .add_plugin(PlayerPlugin)
+        .add_plugin(HazardPlugin)
+        // PLAYER_BACKEND=kinematic 时使用 kinematic character controller
+        .add_plugin(PlayerPlugin {
+            backend: MovementBackend::from_env(),
+        })
+        .add_plugin(PowerUpPlugin)
         .add_plugin(WasdPlugin)
         // 物理引擎插件
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+        .add_plugin(RapierPhysicsPlugin::<OneWayPlatformHooks>::pixels_per_meter(100.0))
         .insert_resource(RapierConfiguration {
             gravity: Vec2::Y * -294.,
             timestep_mode: TimestepMode::Variable {
@@ -50,6 +66,8 @@ fn main() {
             set_clear_color: SetClearColor::FromLevelBackground,
             ..Default::default()
         })
+        // 相邻 level 同时加载，拼接边界上的 collision
+        .insert_resource(CollisionLayerConfig::default().with_stitch_levels(true))
         .add_startup_system(setup)
         .add_system(camera_fit_inside_current_level)
         .run();
@@ -74,7 +92,7 @@ pub fn camera_fit_inside_current_level(
         ),
         Without<Player>,
     >,
-    player_query: Query<&Transform, With<Player>>,
+    player_query: Query<(&Transform, Option<&LookAhead>), With<Player>>,
     level_query: Query<
         (&Transform, &Handle<LdtkLevel>),
         (Without<OrthographicProjection>, Without<Player>),
@@ -82,12 +100,16 @@ pub fn camera_fit_inside_current_level(
     level_selection: Res<LevelSelection>,
     ldtk_levels: Res<Assets<LdtkLevel>>,
 ) {
-    if let Ok(Transform {
-        translation: player_translation,
-        ..
-    }) = player_query.get_single()
+    if let Ok((
+        Transform {
+            translation: player_translation,
+            ..
+        },
+        look_ahead,
+    )) = player_query.get_single()
     {
         let player_translation = *player_translation;
+        let look_offset = look_ahead.map_or(0., |look_ahead| look_ahead.offset);
 
         // 只取第一个，还不知道为什么有多个
         let (mut orthographic_projection, mut camera_transform) = camera_query.iter_mut().next().unwrap();
@@ -109,12 +131,14 @@ pub fn camera_fit_inside_current_level(
                             - level_transform.translation.x
                             - orthographic_projection.right / 2.)
                             .clamp(0., level.px_wid as f32 - orthographic_projection.right);
-                        camera_transform.translation.y = 0.;
+                        // 镜头贴着 level 底边，只在 level 比画面高出的空间里上下看
+                        let room = (level.px_hei as f32 - orthographic_projection.top).max(0.);
+                        camera_transform.translation.y = look_offset.clamp(0., room);
                     } else {
                         // level is taller than the screen
                         orthographic_projection.right = (level.px_wid as f32 / 16.).round() * 16.;
                         orthographic_projection.top = orthographic_projection.right / ASPECT_RATIO;
-                        camera_transform.translation.y = (player_translation.y
+                        camera_transform.translation.y = (player_translation.y + look_offset
                             - level_transform.translation.y
                             - orthographic_projection.top / 2.)
                             .clamp(0., level.px_hei as f32 - orthographic_projection.top);
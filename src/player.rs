@@ -1,88 +1,885 @@
 use bevy::prelude::*;
-use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_ldtk::{ldtk::FieldValue, prelude::*};
 use bevy_rapier2d::prelude::*;
 use leafwing_input_manager::prelude::*;
 
-use crate::{components, wasd};
+use crate::{
+    collision::{OneWayPlatform, Wall, ONE_WAY_GROUP},
+    components, wasd,
+};
 
 use components::*;
 use wasd::*;
 
-pub struct PlayerPlugin;
+///
+/// 玩家插件，backend 决定移动方式
+///
+#[derive(Default)]
+pub struct PlayerPlugin {
+    pub backend: MovementBackend,
+}
+
+///
+/// 玩家的移动方式
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Resource)]
+pub enum MovementBackend {
+    /// 动力学刚体，直接设置速度，由物理引擎处理碰撞
+    #[default]
+    Dynamic,
+    /// Rapier 的 [KinematicCharacterController]，支持自动上台阶、贴地和最大爬坡角度
+    /// 物理钩子对 controller 不生效，单向平台改用 [ONE_WAY_GROUP] 在上升和穿过时排除
+    Kinematic,
+}
+
+/// 选择玩家移动方式的环境变量
+const BACKEND_ENV: &str = "PLAYER_BACKEND";
+
+impl MovementBackend {
+    ///
+    /// 从环境变量 [BACKEND_ENV] 读取，`kinematic` 使用 kinematic controller，其它值使用默认
+    ///
+    pub fn from_env() -> Self {
+        match std::env::var(BACKEND_ENV).as_deref() {
+            Ok("kinematic") => MovementBackend::Kinematic,
+            _ => MovementBackend::default(),
+        }
+    }
+}
+
+///
+/// 玩家相关 system 的执行顺序
+///
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, SystemLabel)]
+pub enum PlayerSystem {
+    Input,
+    Move,
+}
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.register_ldtk_entity::<PlayerBundle>("Player")
-            .add_system(leafwing_input)
-            .add_system(ground_detection);
+            .insert_resource(self.backend)
+            .add_system(leafwing_input.label(PlayerSystem::Input))
+            .add_system(apply_crouch.after(PlayerSystem::Input))
+            .add_system(look_ahead)
+            .add_system(wall_detection);
+
+        match self.backend {
+            MovementBackend::Dynamic => {
+                app.add_system(ground_detection).add_system(
+                    dynamic_movement
+                        .label(PlayerSystem::Move)
+                        .after(PlayerSystem::Input),
+                );
+            }
+            MovementBackend::Kinematic => {
+                app.add_system(setup_kinematic_player)
+                    .add_system(kinematic_output.before(PlayerSystem::Input))
+                    .add_system(
+                        kinematic_movement
+                            .label(PlayerSystem::Move)
+                            .after(PlayerSystem::Input),
+                    );
+            }
+        }
     }
 }
 
-const MOVE_SPEED: f32 = 80.;
+///
+/// 移动手感参数，速度单位是像素/秒
+///
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+pub struct MovementConfig {
+    /// 最大水平速度
+    pub max_speed: f32,
+    /// 按住 Sprint 时的速度倍率
+    pub sprint_multiplier: f32,
+    /// 冲刺跑每秒消耗的体力，为 0 时不受体力限制
+    pub sprint_stamina_cost: f32,
+    /// 不冲刺跑时每秒恢复的体力
+    pub stamina_regen: f32,
+    /// 冲刺的水平距离
+    pub dash_distance: f32,
+    /// 冲刺持续时间，期间无敌
+    pub dash_duration: f32,
+    /// 两次冲刺之间的冷却时间
+    pub dash_cooldown: f32,
+    /// 下蹲时的速度倍率
+    pub crouch_speed_multiplier: f32,
+    /// 有水平输入时的加速度
+    pub acceleration: f32,
+    /// 没有水平输入时的减速度
+    pub deceleration: f32,
+    /// 空中加减速度的倍率，0 表示空中无法改变水平速度
+    pub air_control: f32,
+    /// 起跳速度
+    pub jump_speed: f32,
+    /// 上升时松开跳跃键，竖直速度乘以这个系数，实现可变跳跃高度
+    pub jump_cut: f32,
+    /// 离开地面后仍然可以按地面起跳的时间
+    pub coyote_time: f32,
+    /// 落地前提前按下跳跃，落地后仍然起跳的时间
+    pub jump_buffer_time: f32,
+    /// 朝墙按方向键时的最大下滑速度
+    pub wall_slide_speed: f32,
+    /// 蹬墙跳时远离墙的水平速度
+    pub wall_jump_kick: f32,
+    /// 蹬墙跳对跳跃次数的影响
+    pub wall_jump: WallJumpMode,
+}
+
+///
+/// 蹬墙跳怎么计算跳跃次数
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WallJumpMode {
+    /// 当作从地面起跳，之后可以继续空中跳
+    #[default]
+    Refresh,
+    /// 消耗一次跳跃，没有剩余次数时不能蹬墙跳
+    Consume,
+    /// 不影响跳跃次数
+    Free,
+}
 
+impl WallJumpMode {
+    /// Ldtk 字段里的名字，不区分大小写
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "refresh" => Some(WallJumpMode::Refresh),
+            "consume" => Some(WallJumpMode::Consume),
+            "free" => Some(WallJumpMode::Free),
+            _ => None,
+        }
+    }
+}
+
+impl Default for MovementConfig {
+    fn default() -> Self {
+        Self {
+            max_speed: 80.,
+            sprint_multiplier: 1.6,
+            sprint_stamina_cost: 0.5,
+            stamina_regen: 0.25,
+            dash_distance: 48.,
+            dash_duration: 0.15,
+            dash_cooldown: 0.5,
+            crouch_speed_multiplier: 0.5,
+            acceleration: 800.,
+            deceleration: 1000.,
+            air_control: 0.5,
+            jump_speed: 160.,
+            jump_cut: 0.5,
+            coyote_time: 0.1,
+            jump_buffer_time: 0.1,
+            wall_slide_speed: 40.,
+            wall_jump_kick: 120.,
+            wall_jump: WallJumpMode::Refresh,
+        }
+    }
+}
+
+impl MovementConfig {
+    ///
+    /// 按加减速度把当前水平速度推向目标速度
+    ///
+    fn horizontal_speed(&self, current: f32, target: f32, grounded: bool, delta: f32) -> f32 {
+        let rate = if target != 0. {
+            self.acceleration
+        } else {
+            self.deceleration
+        };
+        let rate = if grounded {
+            rate
+        } else {
+            rate * self.air_control
+        };
+        approach(current, target, rate * delta)
+    }
+
+    ///
+    /// 蹬墙跳时远离墙的水平速度，不是蹬墙跳时返回 None
+    ///
+    fn wall_kick(&self, intent: &MoveIntent, wall_contact: WallContact) -> Option<f32> {
+        if !intent.wall_jump {
+            return None;
+        }
+        Some(-wall_contact.direction() * self.wall_jump_kick)
+    }
+
+    ///
+    /// 朝墙按方向键下滑时限制下落速度
+    ///
+    fn wall_slide(&self, vertical: f32, intent: &MoveIntent, wall_contact: WallContact) -> f32 {
+        let pressing_into_wall =
+            wall_contact != WallContact::None && intent.horizontal * wall_contact.direction() > 0.;
+        if pressing_into_wall && vertical < -self.wall_slide_speed {
+            -self.wall_slide_speed
+        } else {
+            vertical
+        }
+    }
+
+    ///
+    /// 应用起跳和松开跳跃键，返回新的竖直速度
+    ///
+    fn vertical_speed(&self, current: f32, intent: &MoveIntent) -> f32 {
+        if intent.jump {
+            self.jump_speed
+        } else if intent.jump_released && current > 0. {
+            current * self.jump_cut
+        } else {
+            current
+        }
+    }
+}
+
+fn approach(current: f32, target: f32, max_delta: f32) -> f32 {
+    if current < target {
+        (current + max_delta).min(target)
+    } else {
+        (current - max_delta).max(target)
+    }
+}
+
+///
+/// 这一帧玩家实际移动的时间
+/// 动力学刚体按物理步长移动，Variable 模式下一步不超过 max_dt；kinematic controller 按帧时间移动
+///
+fn movement_delta(
+    backend: MovementBackend,
+    rapier_config: &RapierConfiguration,
+    delta: f32,
+) -> f32 {
+    match backend {
+        MovementBackend::Kinematic => delta,
+        MovementBackend::Dynamic => match rapier_config.timestep_mode {
+            TimestepMode::Fixed { dt, .. } => dt,
+            TimestepMode::Variable {
+                max_dt, time_scale, ..
+            } => (delta * time_scale).min(max_dt),
+            TimestepMode::Interpolated { time_scale, .. } => delta * time_scale,
+        },
+    }
+}
+
+///
+/// 土狼时间和跳跃缓冲的计时
+///
+#[derive(Copy, Clone, PartialEq, Default, Debug, Component)]
+pub(crate) struct JumpTimers {
+    coyote: f32,
+    buffer: f32,
+}
+
+///
+/// 一帧里和起跳有关的输入
+///
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+struct JumpInput {
+    /// 站在地面上，起跳后上升时不算
+    on_ground: bool,
+    wall_contact: WallContact,
+    /// 这一帧按下跳跃
+    pressed: bool,
+    /// 正在冲刺，冲刺结束前不起跳
+    dashing: bool,
+}
+
+impl JumpTimers {
+    ///
+    /// 推进一帧土狼时间和跳跃缓冲，更新跳跃次数
+    /// 返回 (这一帧是否起跳, 是否是蹬墙跳)
+    ///
+    fn step(
+        &mut self,
+        jump_flag: &mut JumpFlag,
+        config: &MovementConfig,
+        input: JumpInput,
+        delta: f32,
+    ) -> (bool, bool) {
+        if input.on_ground {
+            jump_flag.clear();
+            self.coyote = config.coyote_time;
+        } else if self.coyote > 0. {
+            self.coyote = (self.coyote - delta).max(0.);
+            // 土狼时间过了还没起跳，地面那一跳作废
+            if self.coyote == 0. && jump_flag.jump_count == 0 {
+                jump_flag.increase_jump_count();
+            }
+        }
+
+        if input.pressed {
+            self.buffer = config.jump_buffer_time;
+        } else if !input.dashing {
+            self.buffer = (self.buffer - delta).max(0.);
+        }
+        // 冲刺期间按下的跳跃留到冲刺结束后再起跳
+        if input.dashing {
+            return (false, false);
+        }
+
+        // 贴墙且不在地面（也不在土狼时间内）时，起跳变成蹬墙跳
+        let wall_jump = self.buffer > 0.
+            && input.wall_contact != WallContact::None
+            && !input.on_ground
+            && self.coyote == 0.;
+        let jump = if wall_jump {
+            match config.wall_jump {
+                WallJumpMode::Refresh => {
+                    jump_flag.clear();
+                    jump_flag.increase_jump_count();
+                    true
+                }
+                WallJumpMode::Consume => {
+                    let can_jump = jump_flag.can_jump();
+                    if can_jump {
+                        jump_flag.increase_jump_count();
+                    }
+                    can_jump
+                }
+                WallJumpMode::Free => true,
+            }
+        } else {
+            let can_jump = self.buffer > 0. && jump_flag.can_jump();
+            if can_jump {
+                jump_flag.increase_jump_count();
+            }
+            can_jump
+        };
+        if jump {
+            self.buffer = 0.;
+            self.coyote = 0.;
+        }
+        (jump, wall_jump && jump)
+    }
+}
+
+///
+/// 把输入转换成 [MoveIntent]，由移动 backend 执行
+///
+#[allow(clippy::type_complexity)]
 fn leafwing_input(
     mut player_query: Query<
         (
-            &mut Velocity,
+            &mut MoveIntent,
+            &mut JumpTimers,
+            &MovementConfig,
+            &Velocity,
             &ActionState<Action>,
             &mut JumpFlag,
             &Grounded,
+            &WallContact,
+            &mut Facing,
+            &mut DropThrough,
+            &mut DashState,
+            &mut Stamina,
+            &mut Health,
+            &Crouch,
         ),
         With<Player>,
     >,
+    platform_query: Query<(), With<OneWayPlatform>>,
+    backend: Res<MovementBackend>,
+    rapier_config: Res<RapierConfiguration>,
+    time: Res<Time>,
 ) {
     if player_query.is_empty() {
         return;
     }
 
-    let (mut velocity, action, mut jump_flag, grounded) = player_query.single_mut();
+    let (
+        mut intent,
+        mut timers,
+        config,
+        velocity,
+        action,
+        mut jump_flag,
+        grounded,
+        wall_contact,
+        mut facing,
+        mut drop_through,
+        mut dash,
+        mut stamina,
+        mut health,
+        crouch,
+    ) = player_query.single_mut();
+    let delta = time.delta_seconds();
 
-    let speed = if action.pressed(wasd::Action::Speed) {
-        MOVE_SPEED * 2.
+    let mut direction = 0.;
+    if action.pressed(wasd::Action::Left) {
+        direction = -1.;
+    }
+    if action.pressed(wasd::Action::Right) {
+        direction = 1.;
+    }
+    // 移动中按住 Sprint 且体力足够时加速，否则恢复体力
+    // 体力耗尽后要恢复一部分或松开 Sprint 才能再跑，避免在跑和走之间来回切换
+    let sprinting = action.pressed(wasd::Action::Sprint)
+        && direction != 0.
+        && stamina.drain(config.sprint_stamina_cost * delta);
+    if !sprinting {
+        stamina.regenerate(config.stamina_regen * delta);
+    }
+    if action.just_released(wasd::Action::Sprint) {
+        stamina.release();
+    }
+    let speed = if crouch.crouching {
+        config.max_speed * config.crouch_speed_multiplier
+    } else if sprinting {
+        config.max_speed * config.sprint_multiplier
     } else {
-        MOVE_SPEED
+        config.max_speed
+    };
+    let horizontal = direction * speed;
+    // 只按一个方向键时更新朝向，同时按住保持原朝向
+    let facing_intent = match (
+        action.pressed(wasd::Action::Left),
+        action.pressed(wasd::Action::Right),
+    ) {
+        (true, false) => Some(Facing::Left),
+        (false, true) => Some(Facing::Right),
+        _ => None,
     };
+    if let Some(facing_intent) = facing_intent {
+        if *facing != facing_intent {
+            *facing = facing_intent;
+        }
+    }
 
-    if action.pressed(wasd::Action::Left) {
-        velocity.linvel.x = -speed;
+    // 起跳的这一帧还贴着地面，上升时不算站在地面上
+    let on_ground = grounded.is_grounded() && velocity.linvel.y <= 0.;
+
+    // 冲刺期间无敌，落地后恢复空中冲刺次数
+    dash.tick(delta);
+    if on_ground {
+        dash.reset_air_dashes();
     }
-    if action.pressed(wasd::Action::Right) {
-        velocity.linvel.x = speed;
+    if action.just_pressed(wasd::Action::Dash) && dash.can_dash(on_ground) {
+        dash.start(
+            facing.direction(),
+            config.dash_distance,
+            config.dash_duration,
+            config.dash_cooldown,
+            on_ground,
+        );
+        health.grant_invulnerability(config.dash_duration);
     }
-    if action.just_pressed(wasd::Action::Jump) && jump_flag.can_jump() {
-        velocity.linvel.y = 160.;
-        jump_flag.increase_jump_count();
+    let dash_speed = dash.advance(movement_delta(*backend, &rapier_config, delta));
+
+    // 站在单向平台上按住 Down 一小会儿穿过去，其它地方按住 Down 只下蹲
+    let on_one_way = grounded
+        .entity
+        .map_or(false, |ground| platform_query.contains(ground));
+    drop_through.hold(on_one_way && action.pressed(wasd::Action::Down), delta);
+    let (jump, wall_jump) = timers.step(
+        &mut jump_flag,
+        config,
+        JumpInput {
+            on_ground,
+            wall_contact: *wall_contact,
+            pressed: action.just_pressed(wasd::Action::Jump),
+            dashing: dash_speed.is_some(),
+        },
+        delta,
+    );
+
+    *intent = MoveIntent {
+        horizontal,
+        jump,
+        wall_jump,
+        jump_released: action.just_released(wasd::Action::Jump),
+        dash: dash_speed,
+        crouch: on_ground && action.pressed(wasd::Action::Down),
+    };
+}
+
+fn dynamic_movement(
+    mut player_query: Query<
+        (
+            &MoveIntent,
+            &MovementConfig,
+            &Grounded,
+            &WallContact,
+            &mut Velocity,
+        ),
+        With<Player>,
+    >,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    for (intent, config, grounded, wall_contact, mut velocity) in &mut player_query {
+        if let Some(dash) = intent.dash {
+            velocity.linvel = Vec2::new(dash, 0.);
+            continue;
+        }
+        velocity.linvel.x = match config.wall_kick(intent, *wall_contact) {
+            Some(kick) => kick,
+            None => config.horizontal_speed(
+                velocity.linvel.x,
+                intent.horizontal,
+                grounded.is_grounded(),
+                delta,
+            ),
+        };
+        velocity.linvel.y = config.vertical_speed(velocity.linvel.y, intent);
+        velocity.linvel.y = config.wall_slide(velocity.linvel.y, intent, *wall_contact);
     }
-    if grounded.0 {
-        jump_flag.clear()
+}
+
+///
+/// kinematic 模式下自己积分的速度
+/// [Velocity] 只用来给动画等读取
+///
+#[derive(Copy, Clone, PartialEq, Default, Debug, Component)]
+pub(crate) struct KinematicMotion {
+    velocity: Vec2,
+}
+
+///
+/// 把 Ldtk 生成的动力学玩家换成 kinematic controller
+///
+fn setup_kinematic_player(mut commands: Commands, query: Query<Entity, Added<Player>>) {
+    for entity in &query {
+        commands.entity(entity).insert((
+            RigidBody::KinematicPositionBased,
+            KinematicCharacterController {
+                autostep: Some(CharacterAutostep {
+                    max_height: CharacterLength::Absolute(4.),
+                    min_width: CharacterLength::Absolute(2.),
+                    include_dynamic_bodies: false,
+                }),
+                snap_to_ground: Some(CharacterLength::Absolute(4.)),
+                max_slope_climb_angle: 50_f32.to_radians(),
+                min_slope_slide_angle: 30_f32.to_radians(),
+                ..default()
+            },
+            KinematicMotion::default(),
+            // kinematic 和 fixed 之间默认不检测，sensor（危险区域、存档点）需要打开
+            ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC,
+        ));
     }
 }
 
-/// 太 6 了，地面检测是 y 轴几帧不动
+#[allow(clippy::type_complexity)]
+fn kinematic_movement(
+    mut player_query: Query<
+        (
+            Entity,
+            &MoveIntent,
+            &MovementConfig,
+            &Grounded,
+            &WallContact,
+            &DropThrough,
+            &GlobalTransform,
+            &Collider,
+            &mut KinematicMotion,
+            &mut KinematicCharacterController,
+            &mut Velocity,
+        ),
+        With<Player>,
+    >,
+    platform_query: Query<(), With<OneWayPlatform>>,
+    rapier_context: Res<RapierContext>,
+    rapier_config: Res<RapierConfiguration>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    for (
+        entity,
+        intent,
+        config,
+        grounded,
+        wall_contact,
+        drop_through,
+        transform,
+        collider,
+        mut motion,
+        mut controller,
+        mut velocity,
+    ) in &mut player_query
+    {
+        // 上升、按住 Down 穿过、或者还卡在平台里时不和单向平台碰撞
+        let on_platform = grounded
+            .entity
+            .map_or(false, |ground| platform_query.contains(ground));
+        let inside_platform = !on_platform && {
+            let is_platform = |other: Entity| platform_query.contains(other);
+            let filter = QueryFilter::default()
+                .exclude_collider(entity)
+                .predicate(&is_platform);
+            rapier_context
+                .intersection_with_shape(transform.translation().truncate(), 0., collider, filter)
+                .is_some()
+        };
+        let pass_one_way =
+            drop_through.is_active() || inside_platform || intent.jump || motion.velocity.y > 0.;
+        controller.filter_groups =
+            pass_one_way.then(|| CollisionGroups::new(Group::ALL, !ONE_WAY_GROUP));
+
+        if let Some(dash) = intent.dash {
+            motion.velocity = Vec2::new(dash, 0.);
+            controller.translation = Some(motion.velocity * delta);
+            velocity.linvel = motion.velocity;
+            continue;
+        }
+        motion.velocity.x = match config.wall_kick(intent, *wall_contact) {
+            Some(kick) => kick,
+            None => config.horizontal_speed(
+                motion.velocity.x,
+                intent.horizontal,
+                grounded.is_grounded(),
+                delta,
+            ),
+        };
+        motion.velocity.y = config.vertical_speed(motion.velocity.y, intent);
+        if grounded.is_grounded() && !intent.jump && motion.velocity.y <= 0. {
+            motion.velocity.y = 0.;
+        } else {
+            motion.velocity.y += rapier_config.gravity.y * delta;
+        }
+        motion.velocity.y = config.wall_slide(motion.velocity.y, intent, *wall_contact);
+
+        controller.translation = Some(motion.velocity * delta);
+        velocity.linvel = motion.velocity;
+    }
+}
+
+///
+/// 读取 controller 上一次移动的结果，更新 [Grounded]，撞到头顶时停止上升
+///
+fn kinematic_output(
+    mut player_query: Query<
+        (
+            &KinematicCharacterControllerOutput,
+            &mut KinematicMotion,
+            &mut Grounded,
+        ),
+        With<Player>,
+    >,
+) {
+    for (output, mut motion, mut grounded) in &mut player_query {
+        let mut ground = Grounded::default();
+        if output.grounded {
+            // 法线最朝上的碰撞作为地面，贴地吸附时可能没有碰撞
+            let mut best = GROUND_NORMAL_Y;
+            for collision in &output.collisions {
+                let normal = collision.toi.normal2;
+                if normal.y > best {
+                    best = normal.y;
+                    ground = Grounded {
+                        entity: Some(collision.entity),
+                        normal,
+                    };
+                }
+            }
+            if ground.entity.is_none() && grounded.is_grounded() {
+                ground = *grounded;
+            }
+        }
+        if *grounded != ground {
+            *grounded = ground;
+        }
+
+        let blocked = output.effective_translation.y < output.desired_translation.y - 0.01;
+        if motion.velocity.y > 0. && blocked {
+            motion.velocity.y = 0.;
+        }
+    }
+}
+
+/// 法线 y 分量超过这个值才算地面，约 53° 以内的斜坡
+const GROUND_NORMAL_Y: f32 = 0.6;
+
+///
+/// 根据玩家碰撞的接触法线判断是否站在地面上
+/// 取法线最朝上的接触作为地面，穿过单向平台时接触被物理钩子清空，不算地面
+///
 fn ground_detection(
-    mut player: Query<(&Transform, &mut Grounded), With<Player>>,
-    mut last: Local<(f32, isize)>,
+    mut player: Query<(Entity, &mut Grounded), With<Player>>,
+    rapier_context: Res<RapierContext>,
 ) {
-    if player.is_empty() {
-        return;
+    for (entity, mut grounded) in &mut player {
+        let mut ground = Grounded::default();
+        let mut best = GROUND_NORMAL_Y;
+        for pair in rapier_context.contacts_with(entity) {
+            if !pair.has_any_active_contacts() {
+                continue;
+            }
+            let other = if pair.collider1() == entity {
+                pair.collider2()
+            } else {
+                pair.collider1()
+            };
+            for manifold in pair.manifolds() {
+                // 法线从 collider1 指向 collider2，统一成从地面指向玩家
+                let normal = if pair.collider1() == entity {
+                    -manifold.normal()
+                } else {
+                    manifold.normal()
+                };
+                if normal.y > best {
+                    best = normal.y;
+                    ground = Grounded {
+                        entity: Some(other),
+                        normal,
+                    };
+                }
+            }
+        }
+        if *grounded != ground {
+            *grounded = ground;
+        }
     }
+}
 
-    let (pos, mut on_ground) = player.single_mut();
+/// 站立时 collider 的半宽和半高
+const PLAYER_HALF_EXTENTS: Vec2 = Vec2::new(9., 16.);
+/// 下蹲时 collider 的半高
+const CROUCH_HALF_HEIGHT: f32 = 10.;
 
-    if (pos.translation.y * 100.).round() == last.0 {
-        last.1 += 1;
-    } else {
-        last.1 -= 1;
-    };
-    last.1 = last.1.clamp(0, 5);
+pub(crate) fn standing_collider() -> Collider {
+    Collider::cuboid(PLAYER_HALF_EXTENTS.x, PLAYER_HALF_EXTENTS.y)
+}
+
+///
+/// 下蹲 collider 向下偏移，保持脚底位置不变
+///
+fn crouching_collider() -> Collider {
+    Collider::compound(vec![(
+        Vec2::new(0., CROUCH_HALF_HEIGHT - PLAYER_HALF_EXTENTS.y),
+        0.,
+        Collider::cuboid(PLAYER_HALF_EXTENTS.x, CROUCH_HALF_HEIGHT),
+    )])
+}
+
+///
+/// 按 [MoveIntent] 切换下蹲，替换 collider
+/// 站起来之前检查头顶空间，被 [Wall] 挡住时保持下蹲
+///
+fn apply_crouch(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &MoveIntent, &GlobalTransform, &mut Crouch), With<Player>>,
+    wall_query: Query<(), With<Wall>>,
+    rapier_context: Res<RapierContext>,
+) {
+    for (entity, intent, transform, mut crouch) in &mut player_query {
+        if intent.crouch == crouch.crouching {
+            continue;
+        }
+        if !intent.crouch {
+            // 略小一点，避免把刚好贴着的地面算成阻挡
+            let standing =
+                Collider::cuboid(PLAYER_HALF_EXTENTS.x - 0.5, PLAYER_HALF_EXTENTS.y - 0.5);
+            let is_wall = |other: Entity| wall_query.contains(other);
+            let filter = QueryFilter::default()
+                .exclude_collider(entity)
+                .exclude_sensors()
+                .predicate(&is_wall);
+            let blocked = rapier_context
+                .intersection_with_shape(transform.translation().truncate(), 0., &standing, filter)
+                .is_some();
+            if blocked {
+                continue;
+            }
+        }
 
-    if last.1 == 5 && !on_ground.0 {
-        on_ground.0 = true;
-    } else if last.1 < 2 && on_ground.0 {
-        on_ground.0 = false;
+        crouch.crouching = intent.crouch;
+        let collider = if crouch.crouching {
+            crouching_collider()
+        } else {
+            standing_collider()
+        };
+        commands.entity(entity).insert(collider);
     }
+}
 
-    last.0 = (pos.translation.y * 100.).round();
+/// 按住 Up/Down 多久后开始移动镜头
+const LOOK_DELAY: f32 = 0.5;
+/// 镜头上下看的最大距离
+const LOOK_DISTANCE: f32 = 48.;
+/// 镜头移动速度
+const LOOK_SPEED: f32 = 120.;
+
+///
+/// 站在地面上不左右移动时，按住 Up 向上看，按住 Down 向下看
+///
+fn look_ahead(
+    mut player_query: Query<
+        (&ActionState<Action>, &Grounded, &MoveIntent, &mut LookAhead),
+        With<Player>,
+    >,
+    time: Res<Time>,
+) {
+    for (action, grounded, intent, mut look_ahead) in &mut player_query {
+        let standing_still = grounded.is_grounded() && intent.horizontal == 0.;
+        let direction = match (
+            action.pressed(wasd::Action::Up),
+            action.pressed(wasd::Action::Down),
+        ) {
+            (true, false) if standing_still => 1.,
+            (false, true) if standing_still => -1.,
+            _ => 0.,
+        };
+        look_ahead.update(
+            direction,
+            LOOK_DELAY,
+            LOOK_DISTANCE,
+            LOOK_SPEED,
+            time.delta_seconds(),
+        );
+    }
+}
+
+/// 贴墙检测向两侧探测的距离
+const WALL_PROBE_DISTANCE: f32 = 2.;
+
+///
+/// 向左右两侧做 shape cast 检测贴墙，只检测 [Wall] collider
+/// 探测用的形状比玩家略矮，避免把脚下的地面当成墙
+///
+fn wall_detection(
+    mut player: Query<(Entity, &GlobalTransform, &Collider, &mut WallContact), With<Player>>,
+    wall_query: Query<(), With<Wall>>,
+    rapier_context: Res<RapierContext>,
+) {
+    for (entity, transform, collider, mut wall_contact) in &mut player {
+        // 下蹲时 collider 是偏移过的 compound，按包围盒构造探测形状
+        let aabb = collider.raw.compute_local_aabb();
+        let half_extents = aabb.half_extents();
+        let center = aabb.center();
+        let probe = Collider::cuboid(half_extents.x, half_extents.y * 0.8);
+        let is_wall = |other: Entity| wall_query.contains(other);
+        let filter = QueryFilter::default()
+            .exclude_collider(entity)
+            .exclude_sensors()
+            .predicate(&is_wall);
+        let position = transform.translation().truncate() + Vec2::new(center.x, center.y);
+
+        let hit = |direction: f32| {
+            rapier_context
+                .cast_shape(
+                    position,
+                    0.,
+                    Vec2::new(direction, 0.),
+                    &probe,
+                    WALL_PROBE_DISTANCE,
+                    filter,
+                )
+                .is_some()
+        };
+        let contact = if hit(1.) {
+            WallContact::Right
+        } else if hit(-1.) {
+            WallContact::Left
+        } else {
+            WallContact::None
+        };
+        if *wall_contact != contact {
+            *wall_contact = contact;
+        }
+    }
 }
 
 #[derive(Bundle, Default, LdtkEntity)]
@@ -90,8 +887,21 @@ struct PlayerBundle {
     sprite: SpriteSheetBundle,
     player: Player,
 
+    #[from_entity_instance]
     jump_flag: JumpFlag,
     grounded: Grounded,
+    facing: Facing,
+    wall_contact: WallContact,
+    drop_through: DropThrough,
+    health: Health,
+    intent: MoveIntent,
+    #[from_entity_instance]
+    movement: MovementConfig,
+    jump_timers: JumpTimers,
+    dash: DashState,
+    stamina: Stamina,
+    crouch: Crouch,
+    look_ahead: LookAhead,
 
     #[bundle]
     input: wasd::InputBundle,
@@ -100,6 +910,63 @@ struct PlayerBundle {
     rapier: PlayerRapierBundle,
 }
 
+///
+/// 读取 Ldtk entity 上的数值字段，Int 和 Float 都可以
+/// 没有这个字段或者为 null 时返回 None
+///
+pub(crate) fn entity_field(entity_instance: &EntityInstance, identifier: &str) -> Option<f32> {
+    let field = entity_instance
+        .field_instances
+        .iter()
+        .find(|field| field.identifier == identifier)?;
+    match field.value {
+        FieldValue::Int(value) => value.map(|value| value as f32),
+        FieldValue::Float(value) => value,
+        _ => None,
+    }
+}
+
+///
+/// 跳跃次数上限来自 Ldtk `Player` 的 `JumpLimit` 字段
+///
+impl From<&EntityInstance> for JumpFlag {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        entity_field(entity_instance, "JumpLimit").map_or_else(JumpFlag::default, |limit| {
+            JumpFlag::with_limit(limit.max(0.) as usize)
+        })
+    }
+}
+
+///
+/// 起跳速度和移动速度来自 Ldtk `Player` 的 `JumpImpulse`、`MoveSpeed` 字段
+/// 蹬墙跳模式来自 `WallJumpMode` 字段，String 或 Enum 都可以
+///
+impl From<&EntityInstance> for MovementConfig {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let mut config = MovementConfig::default();
+        if let Some(jump_impulse) = entity_field(entity_instance, "JumpImpulse") {
+            config.jump_speed = jump_impulse;
+        }
+        if let Some(move_speed) = entity_field(entity_instance, "MoveSpeed") {
+            config.max_speed = move_speed;
+        }
+        let wall_jump = entity_instance
+            .field_instances
+            .iter()
+            .find(|field| field.identifier == "WallJumpMode")
+            .and_then(|field| match &field.value {
+                FieldValue::String(Some(name)) | FieldValue::Enum(Some(name)) => {
+                    WallJumpMode::from_name(name)
+                }
+                _ => None,
+            });
+        if let Some(wall_jump) = wall_jump {
+            config.wall_jump = wall_jump;
+        }
+        config
+    }
+}
+
 #[derive(Bundle)]
 struct PlayerRapierBundle {
     // rigid_body:RigidBody,
@@ -114,8 +981,539 @@ impl Default for PlayerRapierBundle {
         Self {
             velocity: Velocity::default(),
             rigid_body: RigidBody::Dynamic,
-            collider: Collider::cuboid(9., 16.),
+            collider: standing_collider(),
             locked_axes: LockedAxes::ROTATION_LOCKED_Z,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        core::CorePlugin, hierarchy::HierarchyPlugin, transform::TransformPlugin, utils::Duration,
+    };
+
+    use super::*;
+    use crate::collision::{tick_drop_through, OneWayPlatformHooks};
+
+    const DT: f32 = 1. / 60.;
+    const GRAVITY: f32 = -294.;
+
+    ///
+    /// 按帧推进 [JumpTimers]，input 给出每一帧 (是否在地面, 是否按下跳跃)
+    /// 返回起跳的帧
+    ///
+    fn jump_frames(
+        config: &MovementConfig,
+        jump_flag: &mut JumpFlag,
+        frames: usize,
+        input: impl Fn(usize) -> (bool, bool),
+    ) -> Vec<usize> {
+        let mut timers = JumpTimers::default();
+        (0..frames)
+            .filter(|&frame| {
+                let (on_ground, pressed) = input(frame);
+                let input = JumpInput {
+                    on_ground,
+                    pressed,
+                    ..default()
+                };
+                timers.step(jump_flag, config, input, DT).0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn coyote_time_allows_late_ground_jump() {
+        let config = MovementConfig::default();
+        let mut jump_flag = JumpFlag::with_limit(1);
+        // 第 3 帧走出平台，3 帧后按跳跃
+        let jumps = jump_frames(&config, &mut jump_flag, 20, |frame| (frame < 3, frame == 6));
+        assert_eq!(jumps, vec![6]);
+        assert_eq!(jump_flag.jump_count, 1);
+    }
+
+    #[test]
+    fn coyote_time_expiry_forfeits_ground_jump() {
+        let config = MovementConfig::default();
+        let mut jump_flag = JumpFlag::with_limit(1);
+        // 土狼时间 6 帧，第 11 帧才按跳跃
+        let jumps = jump_frames(&config, &mut jump_flag, 20, |frame| {
+            (frame < 3, frame == 11)
+        });
+        assert!(jumps.is_empty());
+        assert_eq!(jump_flag.jump_count, 1);
+
+        // 二段跳时这一下变成空中跳
+        let mut jump_flag = JumpFlag::with_limit(2);
+        let jumps = jump_frames(&config, &mut jump_flag, 20, |frame| {
+            (frame < 3, frame == 11)
+        });
+        assert_eq!(jumps, vec![11]);
+        assert_eq!(jump_flag.jump_count, 2);
+    }
+
+    #[test]
+    fn jump_buffer_hits_and_misses_landing() {
+        let config = MovementConfig::default();
+        // 空中已经用完跳跃次数，第 10 帧落地
+        let landing = |press: usize| {
+            let mut jump_flag = JumpFlag::with_limit(1);
+            jump_flag.increase_jump_count();
+            jump_frames(&config, &mut jump_flag, 20, move |frame| {
+                (frame >= 10, frame == press)
+            })
+        };
+        // 落地前 3 帧按下，落地时起跳
+        assert_eq!(landing(7), vec![10]);
+        // 落地前 8 帧按下，缓冲已经过期
+        assert!(landing(2).is_empty());
+    }
+
+    #[test]
+    fn wall_jump_refreshes_jump_count() {
+        let config = MovementConfig::default();
+        let mut timers = JumpTimers::default();
+        let mut jump_flag = JumpFlag::default();
+        jump_flag.increase_jump_count();
+        jump_flag.increase_jump_count();
+        let input = JumpInput {
+            on_ground: false,
+            wall_contact: WallContact::Left,
+            pressed: true,
+            ..default()
+        };
+        assert_eq!(
+            timers.step(&mut jump_flag, &config, input, DT),
+            (true, true)
+        );
+        assert_eq!(jump_flag.jump_count, 1);
+    }
+
+    /// 空中贴墙按下跳跃，已经用了 used 次跳跃
+    fn wall_jump_with(mode: WallJumpMode, used: usize) -> ((bool, bool), JumpFlag) {
+        let config = MovementConfig {
+            wall_jump: mode,
+            ..default()
+        };
+        let mut timers = JumpTimers::default();
+        let mut jump_flag = JumpFlag::default();
+        for _ in 0..used {
+            jump_flag.increase_jump_count();
+        }
+        let input = JumpInput {
+            wall_contact: WallContact::Right,
+            pressed: true,
+            ..default()
+        };
+        let jumped = timers.step(&mut jump_flag, &config, input, DT);
+        (jumped, jump_flag)
+    }
+
+    #[test]
+    fn consume_wall_jump_needs_a_jump_left() {
+        let (jumped, jump_flag) = wall_jump_with(WallJumpMode::Consume, 1);
+        assert_eq!(jumped, (true, true));
+        assert_eq!(jump_flag.jump_count, 2);
+
+        let (jumped, jump_flag) = wall_jump_with(WallJumpMode::Consume, 2);
+        assert_eq!(jumped, (false, false));
+        assert_eq!(jump_flag.jump_count, 2);
+    }
+
+    #[test]
+    fn free_wall_jump_keeps_jump_count() {
+        let (jumped, jump_flag) = wall_jump_with(WallJumpMode::Free, 2);
+        assert_eq!(jumped, (true, true));
+        assert_eq!(jump_flag.jump_count, 2);
+    }
+
+    #[test]
+    fn wall_jump_mode_from_name() {
+        assert_eq!(
+            WallJumpMode::from_name("Consume"),
+            Some(WallJumpMode::Consume)
+        );
+        assert_eq!(WallJumpMode::from_name("free"), Some(WallJumpMode::Free));
+        assert_eq!(
+            WallJumpMode::from_name("REFRESH"),
+            Some(WallJumpMode::Refresh)
+        );
+        assert_eq!(WallJumpMode::from_name("double"), None);
+    }
+
+    #[test]
+    fn dash_distance_is_fixed_when_physics_step_is_capped() {
+        let rapier_config = RapierConfiguration {
+            timestep_mode: TimestepMode::Variable {
+                max_dt: 1. / 60.,
+                time_scale: 1.,
+                substeps: 1,
+            },
+            ..default()
+        };
+        let config = MovementConfig::default();
+        // 帧时间忽长忽短，超过 max_dt 的帧物理只走 max_dt
+        let deltas = [0.04, 0.01, 0.03, 0.04, 0.005, 0.04];
+        for backend in [MovementBackend::Dynamic, MovementBackend::Kinematic] {
+            let mut dash = DashState::default();
+            dash.start(1., config.dash_distance, config.dash_duration, 0., true);
+            let mut distance = 0.;
+            for &delta in deltas.iter().cycle().take(60) {
+                let step = movement_delta(backend, &rapier_config, delta);
+                if let Some(speed) = dash.advance(step) {
+                    distance += speed * step;
+                }
+            }
+            assert!(!dash.is_dashing());
+            assert!(
+                (distance - config.dash_distance).abs() < 1e-3,
+                "{:?}: {}",
+                backend,
+                distance
+            );
+        }
+    }
+
+    #[test]
+    fn jump_pressed_during_dash_fires_after_dash() {
+        let config = MovementConfig::default();
+        let mut timers = JumpTimers::default();
+        let mut jump_flag = JumpFlag::default();
+        // 空中冲刺 12 帧，比跳跃缓冲长，第 1 帧按下跳跃
+        let jumps: Vec<usize> = (0..20)
+            .filter(|&frame| {
+                let input = JumpInput {
+                    pressed: frame == 1,
+                    dashing: frame < 12,
+                    ..default()
+                };
+                timers.step(&mut jump_flag, &config, input, DT).0
+            })
+            .collect();
+        assert_eq!(jumps, vec![12]);
+        assert_eq!(jump_flag.jump_count, 1);
+    }
+
+    ///
+    /// 从地面起跳，released 帧松开跳跃键，返回最高点
+    ///
+    fn jump_apex(config: &MovementConfig, released: Option<usize>) -> f32 {
+        let (mut y, mut vy, mut apex) = (0., 0., 0_f32);
+        for frame in 0..120 {
+            let intent = MoveIntent {
+                jump: frame == 0,
+                jump_released: released == Some(frame),
+                ..default()
+            };
+            vy = config.vertical_speed(vy, &intent) + GRAVITY * DT;
+            y += vy * DT;
+            apex = apex.max(y);
+        }
+        apex
+    }
+
+    #[test]
+    fn jump_cut_on_release_lowers_apex() {
+        let config = MovementConfig::default();
+        let full = jump_apex(&config, None);
+        let expected = config.jump_speed * config.jump_speed / (2. * -GRAVITY);
+        assert!((full - expected).abs() < 2., "{} vs {}", full, expected);
+
+        let cut = jump_apex(&config, Some(6));
+        assert!(cut < full * 0.6, "{} vs {}", cut, full);
+        // 下落时松开不影响
+        assert_eq!(jump_apex(&config, Some(60)), full);
+    }
+
+    #[test]
+    fn vertical_speed_only_cuts_while_rising() {
+        let config = MovementConfig::default();
+        let released = MoveIntent {
+            jump_released: true,
+            ..default()
+        };
+        assert_eq!(
+            config.vertical_speed(100., &released),
+            100. * config.jump_cut
+        );
+        assert_eq!(config.vertical_speed(-100., &released), -100.);
+        let jump = MoveIntent {
+            jump: true,
+            ..default()
+        };
+        assert_eq!(config.vertical_speed(-100., &jump), config.jump_speed);
+    }
+
+    /// 从 start 按固定帧长推进 frames 帧后的水平速度
+    fn horizontal_after(start: f32, target: f32, grounded: bool, frames: usize) -> f32 {
+        let config = MovementConfig::default();
+        (0..frames).fold(start, |speed, _| {
+            config.horizontal_speed(speed, target, grounded, DT)
+        })
+    }
+
+    #[test]
+    fn acceleration_and_air_control_curves() {
+        let config = MovementConfig::default();
+        let max = config.max_speed;
+        // 地面 800/s，3 帧到 40，0.1 秒后达到最大速度并保持
+        assert!((horizontal_after(0., max, true, 3) - 40.).abs() < 1e-3);
+        assert_eq!(horizontal_after(0., max, true, 10), max);
+        // 空中加速度减半
+        assert!((horizontal_after(0., max, false, 3) - 20.).abs() < 1e-3);
+        assert_eq!(horizontal_after(0., -max, false, 30), -max);
+        // 松开方向键按 1000/s 减速，不会越过 0
+        assert!((horizontal_after(max, 0., true, 2) - (max - 2000. / 60.)).abs() < 1e-3);
+        assert_eq!(horizontal_after(max, 0., true, 5), 0.);
+        assert_eq!(approach(5., 0., 10.), 0.);
+        assert_eq!(approach(-5., 0., 10.), 0.);
+    }
+
+    /// 物理按 [DT] 固定步长，Time 也由 [step] 按这个步长手动推进
+    fn rapier_app() -> App {
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.add_plugin(CorePlugin::default())
+            .insert_resource(time)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Mesh>()
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .add_plugin(RapierPhysicsPlugin::<OneWayPlatformHooks>::pixels_per_meter(100.0))
+            .insert_resource(RapierConfiguration {
+                gravity: Vec2::Y * GRAVITY,
+                timestep_mode: TimestepMode::Fixed {
+                    dt: DT,
+                    substeps: 1,
+                },
+                ..default()
+            });
+        app
+    }
+
+    /// 动力学刚体玩家
+    fn physics_app() -> App {
+        let mut app = rapier_app();
+        app.add_system(ground_detection)
+            .add_system(tick_drop_through);
+        app
+    }
+
+    /// kinematic controller 玩家，不读输入，由测试直接设置 [MoveIntent]
+    fn kinematic_app() -> App {
+        let mut app = rapier_app();
+        app.add_system(setup_kinematic_player)
+            .add_system(kinematic_output.before(kinematic_movement))
+            .add_system(kinematic_movement)
+            .add_system(tick_drop_through);
+        app
+    }
+
+    fn spawn_kinematic_player(app: &mut App, config: MovementConfig, x: f32, y: f32) -> Entity {
+        let player = spawn_player(app, x, y);
+        app.world.entity_mut(player).insert((
+            config,
+            MoveIntent::default(),
+            WallContact::default(),
+        ));
+        player
+    }
+
+    fn at(x: f32, y: f32) -> TransformBundle {
+        let transform = Transform::from_xyz(x, y, 0.);
+        TransformBundle {
+            local: transform,
+            global: GlobalTransform::from(transform),
+        }
+    }
+
+    fn spawn_player(app: &mut App, x: f32, y: f32) -> Entity {
+        app.world
+            .spawn((
+                Player,
+                Grounded::default(),
+                DropThrough::default(),
+                RigidBody::Dynamic,
+                standing_collider(),
+                LockedAxes::ROTATION_LOCKED_Z,
+                Velocity::default(),
+                at(x, y),
+            ))
+            .id()
+    }
+
+    fn step(app: &mut App, frames: usize) {
+        for _ in 0..frames {
+            let mut time = app.world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(DT));
+            app.update();
+        }
+    }
+
+    fn grounded(app: &App, player: Entity) -> Grounded {
+        *app.world.get::<Grounded>(player).unwrap()
+    }
+
+    #[test]
+    fn resting_on_fixed_cuboid_is_grounded() {
+        let mut app = physics_app();
+        let ground = app
+            .world
+            .spawn((RigidBody::Fixed, Collider::cuboid(200., 8.), at(0., 0.)))
+            .id();
+        let player = spawn_player(&mut app, 0., 8. + PLAYER_HALF_EXTENTS.y + 1.);
+        step(&mut app, 60);
+
+        let grounded = grounded(&app, player);
+        assert_eq!(grounded.entity, Some(ground));
+        assert!(grounded.normal.y > 0.99, "{:?}", grounded.normal);
+    }
+
+    #[test]
+    fn not_grounded_at_jump_apex() {
+        let mut app = physics_app();
+        app.world
+            .spawn((RigidBody::Fixed, Collider::cuboid(200., 8.), at(0., 0.)));
+        let player = spawn_player(&mut app, 0., 8. + PLAYER_HALF_EXTENTS.y + 1.);
+        step(&mut app, 60);
+        assert!(grounded(&app, player).is_grounded());
+
+        app.world.get_mut::<Velocity>(player).unwrap().linvel.y = 160.;
+        let mut frames = 0;
+        while app.world.get::<Velocity>(player).unwrap().linvel.y > 0. {
+            step(&mut app, 1);
+            frames += 1;
+            assert!(frames < 120, "never reached the apex");
+        }
+        assert_eq!(grounded(&app, player), Grounded::default());
+    }
+
+    #[test]
+    fn wall_contact_alone_is_not_ground() {
+        let mut app = physics_app();
+        let wall = app
+            .world
+            .spawn((RigidBody::Fixed, Collider::cuboid(8., 400.), at(0., 0.)))
+            .id();
+        let player = spawn_player(&mut app, 8. + PLAYER_HALF_EXTENTS.x, 0.);
+        app.world.get_mut::<Velocity>(player).unwrap().linvel.x = -30.;
+        step(&mut app, 5);
+
+        let touching_wall = app
+            .world
+            .resource::<RapierContext>()
+            .contact_pair(player, wall)
+            .map_or(false, |pair| pair.has_any_active_contacts());
+        assert!(touching_wall);
+        assert_eq!(grounded(&app, player), Grounded::default());
+    }
+
+    #[test]
+    fn not_grounded_while_dropping_through() {
+        let mut app = physics_app();
+        let platform = app
+            .world
+            .spawn((
+                RigidBody::Fixed,
+                Collider::cuboid(100., 2.),
+                ActiveHooks::MODIFY_SOLVER_CONTACTS,
+                OneWayPlatform,
+                at(0., 0.),
+            ))
+            .id();
+        let player = spawn_player(&mut app, 0., 2. + PLAYER_HALF_EXTENTS.y + 1.);
+        step(&mut app, 60);
+        assert_eq!(grounded(&app, player).entity, Some(platform));
+
+        app.world.get_mut::<DropThrough>(player).unwrap().start();
+        step(&mut app, 2);
+        assert_eq!(grounded(&app, player), Grounded::default());
+
+        // 计时结束时玩家还穿插在平台里
+        step(&mut app, 16);
+        assert!(!app.world.get::<DropThrough>(player).unwrap().is_active());
+        let y = app.world.get::<Transform>(player).unwrap().translation.y;
+        assert!(
+            y - PLAYER_HALF_EXTENTS.y < 2. && y + PLAYER_HALF_EXTENTS.y > -2.,
+            "{}",
+            y
+        );
+
+        // 不会被弹回平台上，一直穿过平台往下掉
+        for _ in 0..30 {
+            step(&mut app, 1);
+            assert_eq!(grounded(&app, player), Grounded::default());
+            assert!(app.world.get::<Velocity>(player).unwrap().linvel.y < 0.);
+        }
+        let y = app.world.get::<Transform>(player).unwrap().translation.y;
+        assert!(y < -2. - PLAYER_HALF_EXTENTS.y, "{}", y);
+    }
+
+    #[test]
+    fn kinematic_player_lands_and_walks() {
+        let mut app = kinematic_app();
+        let ground = app
+            .world
+            .spawn((RigidBody::Fixed, Collider::cuboid(200., 8.), at(0., 0.)))
+            .id();
+        let player = spawn_kinematic_player(
+            &mut app,
+            MovementConfig::default(),
+            0.,
+            8. + PLAYER_HALF_EXTENTS.y + 10.,
+        );
+        step(&mut app, 60);
+
+        assert_eq!(grounded(&app, player).entity, Some(ground));
+        assert_eq!(
+            app.world.get::<KinematicMotion>(player).unwrap().velocity.y,
+            0.
+        );
+        let y = app.world.get::<Transform>(player).unwrap().translation.y;
+        assert!((y - 8. - PLAYER_HALF_EXTENTS.y).abs() < 1., "{}", y);
+
+        app.world.get_mut::<MoveIntent>(player).unwrap().horizontal = 80.;
+        step(&mut app, 30);
+        assert_eq!(grounded(&app, player).entity, Some(ground));
+        let x = app.world.get::<Transform>(player).unwrap().translation.x;
+        assert!(x > 30., "{}", x);
+    }
+
+    #[test]
+    fn kinematic_player_jumps_up_through_one_way_platform() {
+        let mut app = kinematic_app();
+        app.world
+            .spawn((RigidBody::Fixed, Collider::cuboid(200., 8.), at(0., 0.)));
+        let platform = app
+            .world
+            .spawn((
+                RigidBody::Fixed,
+                Collider::cuboid(100., 2.),
+                CollisionGroups::new(ONE_WAY_GROUP, Group::ALL),
+                OneWayPlatform,
+                at(0., 60.),
+            ))
+            .id();
+        // 跳跃高度约 98 像素，能从地面穿过平台
+        let config = MovementConfig {
+            jump_speed: 240.,
+            ..default()
+        };
+        let player = spawn_kinematic_player(&mut app, config, 0., 8. + PLAYER_HALF_EXTENTS.y + 1.);
+        step(&mut app, 30);
+        assert!(grounded(&app, player).is_grounded());
+
+        app.world.get_mut::<MoveIntent>(player).unwrap().jump = true;
+        step(&mut app, 1);
+        app.world.get_mut::<MoveIntent>(player).unwrap().jump = false;
+        step(&mut app, 90);
+
+        // 上升时穿过平台，落下时站在平台上
+        assert_eq!(grounded(&app, player).entity, Some(platform));
+        let y = app.world.get::<Transform>(player).unwrap().translation.y;
+        assert!((y - 62. - PLAYER_HALF_EXTENTS.y).abs() < 1., "{}", y);
+    }
+}
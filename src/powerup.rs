@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::components::{JumpFlag, Player};
+use crate::player::entity_field;
+
+///
+/// 道具插件
+/// 玩家碰到 Ldtk `JumpPowerUp` entity 时提高跳跃次数上限
+///
+pub struct PowerUpPlugin;
+
+impl Plugin for PowerUpPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<JumpPowerUpBundle>("JumpPowerUp")
+            .add_system(collect_jump_power_up);
+    }
+}
+
+///
+/// 跳跃次数道具，amount 来自 Ldtk 的 `Amount` 字段
+///
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Component)]
+pub struct JumpPowerUp {
+    pub amount: usize,
+}
+
+impl Default for JumpPowerUp {
+    fn default() -> Self {
+        Self { amount: 1 }
+    }
+}
+
+impl From<&EntityInstance> for JumpPowerUp {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        entity_field(entity_instance, "Amount").map_or_else(JumpPowerUp::default, |amount| {
+            JumpPowerUp {
+                amount: amount.max(0.) as usize,
+            }
+        })
+    }
+}
+
+#[derive(Bundle, Default, LdtkEntity)]
+struct JumpPowerUpBundle {
+    #[from_entity_instance]
+    power_up: JumpPowerUp,
+
+    #[bundle]
+    sensor: PowerUpSensorBundle,
+}
+
+#[derive(Bundle)]
+struct PowerUpSensorBundle {
+    collider: Collider,
+    sensor: Sensor,
+}
+
+impl Default for PowerUpSensorBundle {
+    fn default() -> Self {
+        Self {
+            collider: Collider::ball(8.),
+            sensor: Sensor,
+        }
+    }
+}
+
+fn collect_jump_power_up(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &mut JumpFlag), With<Player>>,
+    power_up_query: Query<&JumpPowerUp>,
+    rapier_context: Res<RapierContext>,
+) {
+    for (player, mut jump_flag) in &mut player_query {
+        for (collider1, collider2, intersecting) in rapier_context.intersections_with(player) {
+            if !intersecting {
+                continue;
+            }
+            let other = if collider1 == player {
+                collider2
+            } else {
+                collider1
+            };
+            let Ok(power_up) = power_up_query.get(other) else {
+                continue;
+            };
+
+            jump_flag.raise_limit(power_up.amount);
+            info!("Jump limit raised to {}", jump_flag.jump_limit());
+            commands.entity(other).despawn_recursive();
+        }
+    }
+}
@@ -1,22 +1,50 @@
+use std::{fs, path::PathBuf};
+
 use bevy::prelude::*;
-use leafwing_input_manager::prelude::*;
+use leafwing_input_manager::{prelude::*, user_input::UserInput};
+use serde::{Deserialize, Serialize};
 
 pub struct WasdPlugin;
 
 impl Plugin for WasdPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(InputManagerPlugin::<Action>::default());
+        app.add_plugin(InputManagerPlugin::<Action>::default())
+            .init_resource::<InputProfile>()
+            .init_resource::<KeyRebinding>()
+            .add_event::<RebindResult>()
+            .add_system(capture_rebinding)
+            // 在 capture_rebinding 之后开始改键，按下的 F1/F2 不会被当成新按键
+            .add_system(debug_rebinding.after(capture_rebinding))
+            .add_system(log_rebind_results.after(capture_rebinding))
+            .add_system(apply_input_profile.after(capture_rebinding));
     }
 }
 
-#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug)]
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
 pub enum Action {
     Up,
     Down,
     Right,
     Left,
     Jump,
-    Speed,
+    Sprint,
+    Dash,
+}
+
+/// 按键配置文件，相对于工作目录
+const INPUT_PROFILE_PATH: &str = "config/input.ron";
+
+fn default_input_map() -> InputMap<Action> {
+    use Action::*;
+    InputMap::new([
+        (KeyCode::W, Up),
+        (KeyCode::A, Left),
+        (KeyCode::S, Down),
+        (KeyCode::D, Right),
+        (KeyCode::Space, Jump),
+        (KeyCode::LShift, Sprint),
+        (KeyCode::J, Dash),
+    ])
 }
 
 #[derive(Bundle)]
@@ -27,19 +55,280 @@ pub struct InputBundle {
 
 impl Default for InputBundle {
     fn default() -> Self {
-        use Action::*;
         Self {
             input_manager: InputManagerBundle {
-                input_map: InputMap::new([
-                    (KeyCode::W, Up),
-                    (KeyCode::A, Left),
-                    (KeyCode::S, Down),
-                    (KeyCode::D, Right),
-                    (KeyCode::Space, Jump),
-                    (KeyCode::LShift, Speed),
-                ]),
+                input_map: default_input_map(),
                 ..default()
             },
         }
     }
 }
+
+///
+/// 当前的按键配置，启动时从 [INPUT_PROFILE_PATH] 读取
+/// 文件不存在或者解析失败时使用默认配置
+///
+#[derive(Debug, Clone, Resource)]
+pub struct InputProfile {
+    pub map: InputMap<Action>,
+    path: PathBuf,
+}
+
+impl Default for InputProfile {
+    fn default() -> Self {
+        Self::load(INPUT_PROFILE_PATH)
+    }
+}
+
+impl InputProfile {
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let map = match fs::read_to_string(&path) {
+            Ok(content) => ron::from_str(&content).unwrap_or_else(|err| {
+                error!("Failed to parse input profile {:?}: {}", path, err);
+                default_input_map()
+            }),
+            Err(_) => default_input_map(),
+        };
+        Self { map, path }
+    }
+
+    pub fn save(&self) {
+        let content = match ron::ser::to_string_pretty(&self.map, ron::ser::PrettyConfig::default())
+        {
+            Ok(content) => content,
+            Err(err) => {
+                error!("Failed to serialize input profile: {}", err);
+                return;
+            }
+        };
+        if let Some(dir) = self.path.parent() {
+            if let Err(err) = fs::create_dir_all(dir) {
+                error!(
+                    "Failed to create input profile directory {:?}: {}",
+                    dir, err
+                );
+                return;
+            }
+        }
+        if let Err(err) = fs::write(&self.path, content) {
+            error!("Failed to write input profile {:?}: {}", self.path, err);
+        }
+    }
+
+    ///
+    /// 已经绑定了这个按键的其它 action
+    ///
+    pub fn conflicts(&self, action: Action, key: KeyCode) -> Vec<Action> {
+        let input = UserInput::from(key);
+        Action::variants()
+            .filter(|&other| other != action)
+            .filter(|&other| self.map.get(other).iter().any(|bound| *bound == input))
+            .collect()
+    }
+
+    ///
+    /// 把 action 绑定到 key，替换原来的所有按键
+    ///
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        self.map.clear_action(action);
+        self.map.insert(key, action);
+    }
+}
+
+///
+/// 改键，调用 [KeyRebinding::capture] 后下一次按下的键会绑定到这个 action
+/// 按 Escape 取消
+///
+#[derive(Debug, Clone, Default, Resource)]
+pub struct KeyRebinding {
+    pending: Option<Action>,
+}
+
+impl KeyRebinding {
+    pub fn capture(&mut self, action: Action) {
+        self.pending = Some(action);
+    }
+
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    pub fn pending(&self) -> Option<Action> {
+        self.pending
+    }
+}
+
+///
+/// 改键的结果
+/// 按键已被其它 action 使用时不会绑定，由调用方决定是否换一个键
+///
+#[derive(Debug, Clone)]
+pub enum RebindResult {
+    Bound {
+        action: Action,
+        key: KeyCode,
+    },
+    Conflict {
+        action: Action,
+        key: KeyCode,
+        with: Vec<Action>,
+    },
+    Cancelled {
+        action: Action,
+    },
+}
+
+fn capture_rebinding(
+    mut rebinding: ResMut<KeyRebinding>,
+    mut profile: ResMut<InputProfile>,
+    keys: Res<Input<KeyCode>>,
+    mut results: EventWriter<RebindResult>,
+) {
+    let Some(action) = rebinding.pending else {
+        return;
+    };
+    let Some(&key) = keys.get_just_pressed().next() else {
+        return;
+    };
+    rebinding.cancel();
+
+    if key == KeyCode::Escape {
+        results.send(RebindResult::Cancelled { action });
+        return;
+    }
+
+    let conflicts = profile.conflicts(action, key);
+    if !conflicts.is_empty() {
+        results.send(RebindResult::Conflict {
+            action,
+            key,
+            with: conflicts,
+        });
+        return;
+    }
+
+    profile.bind(action, key);
+    profile.save();
+    results.send(RebindResult::Bound { action, key });
+}
+
+///
+/// 开发用的改键入口：按 F1 改 Jump，按 F2 改 Dash
+///
+fn debug_rebinding(keys: Res<Input<KeyCode>>, mut rebinding: ResMut<KeyRebinding>) {
+    // 这一帧刚完成改键时，按下的键已经用掉了
+    if rebinding.pending().is_some() || rebinding.is_changed() {
+        return;
+    }
+    let action = if keys.just_pressed(KeyCode::F1) {
+        Action::Jump
+    } else if keys.just_pressed(KeyCode::F2) {
+        Action::Dash
+    } else {
+        return;
+    };
+    rebinding.capture(action);
+    info!("Press a key for {:?}, Escape to cancel", action);
+}
+
+fn log_rebind_results(mut results: EventReader<RebindResult>) {
+    for result in results.iter() {
+        match result {
+            RebindResult::Bound { action, key } => info!("Bound {:?} to {:?}", action, key),
+            RebindResult::Conflict { action, key, with } => warn!(
+                "{:?} is already bound to {:?}, {:?} was not rebound",
+                key, with, action
+            ),
+            RebindResult::Cancelled { action } => info!("Rebinding {:?} cancelled", action),
+        }
+    }
+}
+
+///
+/// 配置变化或者新生成玩家时，把按键配置同步到 [InputMap]
+///
+fn apply_input_profile(
+    profile: Res<InputProfile>,
+    mut query: Query<(&mut InputMap<Action>, ChangeTrackers<InputMap<Action>>)>,
+) {
+    for (mut input_map, tracker) in &mut query {
+        if profile.is_changed() || tracker.is_added() {
+            *input_map = profile.map.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每个测试用自己的临时目录，不碰 [INPUT_PROFILE_PATH]
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ldtk-input-{}-{}", std::process::id(), name))
+    }
+
+    fn bindings(map: &InputMap<Action>) -> Vec<(Action, Vec<UserInput>)> {
+        Action::variants()
+            .map(|action| (action, map.get(action).iter().cloned().collect()))
+            .collect()
+    }
+
+    #[test]
+    fn input_map_round_trips_through_ron() {
+        let map = default_input_map();
+        let content = ron::ser::to_string_pretty(&map, ron::ser::PrettyConfig::default()).unwrap();
+        let parsed: InputMap<Action> = ron::from_str(&content).unwrap();
+        assert_eq!(bindings(&parsed), bindings(&map));
+    }
+
+    #[test]
+    fn load_falls_back_to_default() {
+        let dir = temp_dir("fallback");
+        let missing = InputProfile::load(dir.join("missing.ron"));
+        assert_eq!(bindings(&missing.map), bindings(&default_input_map()));
+
+        fs::create_dir_all(&dir).unwrap();
+        let invalid = dir.join("invalid.ron");
+        fs::write(&invalid, "not an input map").unwrap();
+        let profile = InputProfile::load(&invalid);
+        assert_eq!(bindings(&profile.map), bindings(&default_input_map()));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn conflicts_skip_the_action_itself() {
+        let profile = InputProfile::load(temp_dir("conflicts").join("input.ron"));
+        assert_eq!(
+            profile.conflicts(Action::Jump, KeyCode::W),
+            vec![Action::Up]
+        );
+        assert!(profile.conflicts(Action::Up, KeyCode::W).is_empty());
+        assert!(profile.conflicts(Action::Jump, KeyCode::K).is_empty());
+    }
+
+    #[test]
+    fn bind_replaces_keys_and_persists() {
+        let dir = temp_dir("bind");
+        // 保存时创建不存在的目录
+        let path = dir.join("nested").join("input.ron");
+        let mut profile = InputProfile::load(&path);
+        profile.bind(Action::Jump, KeyCode::K);
+        assert_eq!(
+            bindings(&profile.map)
+                .into_iter()
+                .find(|(action, _)| *action == Action::Jump)
+                .unwrap()
+                .1,
+            vec![UserInput::from(KeyCode::K)]
+        );
+        assert!(profile.conflicts(Action::Dash, KeyCode::Space).is_empty());
+
+        profile.save();
+        let loaded = InputProfile::load(&path);
+        assert_eq!(bindings(&loaded.map), bindings(&profile.map));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}